@@ -1,3 +1,28 @@
 fn main() {
-    tauri_build::build()
+    export_dependency_versions(&["mozjpeg-sys", "oxipng", "ravif"]);
+    #[cfg(feature = "gui")]
+    tauri_build::build();
+}
+
+/// Exposes the locked versions of the encoder crates as
+/// `STRETTA_<CRATE>_VERSION` for the diagnostics report.
+fn export_dependency_versions(crates: &[&str]) {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+
+    for name in crates {
+        let version = locked_version(&lock, name).unwrap_or("unknown");
+        let var = name.to_uppercase().replace('-', "_");
+        println!("cargo:rustc-env=STRETTA_{}_VERSION={}", var, version);
+    }
+}
+
+fn locked_version<'a>(lock: &'a str, name: &str) -> Option<&'a str> {
+    let name_line = format!("name = \"{}\"", name);
+    let mut lines = lock.lines();
+    lines.find(|line| *line == name_line)?;
+    lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')
 }
@@ -0,0 +1,61 @@
+use image::{DynamicImage, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use crate::utility::CompressionMethod;
+
+/// What happens to transparent inputs under a method whose output has no
+/// alpha channel, saved as the `alpha` block of `AppSettings`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AlphaOptions {
+    pub action: AlphaAction,
+    /// Colour transparent pixels are blended onto when flattening.
+    pub background: [u8; 3],
+    /// Method transparent inputs are sent to by `AlphaAction::Reroute`. It has
+    /// to keep transparency itself.
+    pub reroute_method: CompressionMethod,
+}
+
+impl Default for AlphaOptions {
+    fn default() -> Self {
+        Self {
+            action: AlphaAction::default(),
+            background: [255, 255, 255],
+            reroute_method: CompressionMethod::WebpLossy,
+        }
+    }
+}
+
+/// Also reported in `CompressionResult` for every input it was applied to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlphaAction {
+    /// Blend onto `AlphaOptions::background` and encode as usual.
+    #[default]
+    Flatten,
+    /// Leave the input alone and copy it to the output directory as it is.
+    KeepOriginal,
+    /// Compress with `AlphaOptions::reroute_method` instead.
+    Reroute,
+}
+
+/// Whether any pixel is less than fully opaque. Images with an alpha channel
+/// that's opaque everywhere lose nothing when it's dropped.
+pub fn has_transparency(image: &DynamicImage) -> bool {
+    image.color().has_alpha() && image.to_rgba8().pixels().any(|pixel| pixel[3] < u8::MAX)
+}
+
+/// Blends `image` onto a solid `background`, leaving no alpha channel.
+pub fn flatten(image: &DynamicImage, background: [u8; 3]) -> DynamicImage {
+    let rgba = image.to_rgba8();
+    let flattened = RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let pixel = rgba.get_pixel(x, y);
+        let alpha = u32::from(pixel[3]);
+        Rgb(std::array::from_fn(|channel| {
+            let blended =
+                u32::from(pixel[channel]) * alpha + u32::from(background[channel]) * (255 - alpha);
+            ((blended + 127) / 255) as u8
+        }))
+    });
+    DynamicImage::ImageRgb8(flattened)
+}
@@ -0,0 +1,102 @@
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::metadata::Orientation;
+use image::{AnimationDecoder, DynamicImage, Frame, ImageFormat, ImageResult};
+use std::io::Cursor;
+
+use crate::error::{Result, StrettaError};
+
+/// Every frame of an animated input, composited onto the full canvas.
+pub struct Animation {
+    pub frames: Vec<Frame>,
+    /// How many times the animation plays, 0 for forever.
+    pub loop_count: u32,
+}
+
+impl Animation {
+    /// Decodes every frame of `bytes`. `None` for still images and formats
+    /// without animation support.
+    pub fn decode(bytes: &[u8]) -> Result<Option<Self>> {
+        match image::guess_format(bytes).ok() {
+            Some(ImageFormat::Gif) => decode_gif(bytes),
+            Some(ImageFormat::Png) => decode_apng(bytes),
+            Some(ImageFormat::WebP) => decode_webp(bytes),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn canvas_size(&self) -> (u32, u32) {
+        self.frames[0].buffer().dimensions()
+    }
+
+    /// Rotates and flips every frame the way an EXIF orientation says.
+    pub fn apply_orientation(&mut self, orientation: Orientation) {
+        if orientation == Orientation::NoTransforms {
+            return;
+        }
+        for frame in &mut self.frames {
+            let mut image = DynamicImage::ImageRgba8(frame.buffer().clone());
+            image.apply_orientation(orientation);
+            *frame = Frame::from_parts(image.into_rgba8(), 0, 0, frame.delay());
+        }
+    }
+}
+
+/// Single-frame animations count as still images.
+fn animation(frames: ImageResult<Vec<Frame>>, loop_count: u32) -> Result<Option<Animation>> {
+    let frames = frames.map_err(|e| StrettaError::Decode(e.to_string()))?;
+    Ok((frames.len() > 1).then_some(Animation { frames, loop_count }))
+}
+
+fn decode_gif(bytes: &[u8]) -> Result<Option<Animation>> {
+    let frames = GifDecoder::new(Cursor::new(bytes))
+        .and_then(|decoder| decoder.into_frames().collect_frames());
+    animation(frames, gif_loop_count(bytes))
+}
+
+fn decode_apng(bytes: &[u8]) -> Result<Option<Animation>> {
+    let Some(control) = png::Decoder::new(bytes)
+        .read_info()
+        .ok()
+        .and_then(|reader| reader.info().animation_control)
+    else {
+        return Ok(None);
+    };
+
+    let frames = PngDecoder::new(Cursor::new(bytes))
+        .and_then(|decoder| decoder.apng())
+        .and_then(|decoder| decoder.into_frames().collect_frames());
+    animation(frames, control.num_plays)
+}
+
+fn decode_webp(bytes: &[u8]) -> Result<Option<Animation>> {
+    let loop_count = match image_webp::WebPDecoder::new(Cursor::new(bytes)) {
+        Ok(decoder) if decoder.is_animated() => match decoder.loop_count() {
+            image_webp::LoopCount::Forever => 0,
+            image_webp::LoopCount::Times(plays) => u32::from(plays.get()),
+        },
+        _ => return Ok(None),
+    };
+
+    let frames = WebPDecoder::new(Cursor::new(bytes))
+        .and_then(|decoder| decoder.into_frames().collect_frames());
+    animation(frames, loop_count)
+}
+
+/// GIF stores how many times to repeat after the first play, and plays once
+/// when the NETSCAPE extension is missing.
+fn gif_loop_count(bytes: &[u8]) -> u32 {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let Ok(mut decoder) = options.read_info(bytes) else {
+        return 0;
+    };
+    // The extension comes before the first frame
+    let _ = decoder.next_frame_info();
+
+    match decoder.repeat() {
+        gif::Repeat::Infinite => 0,
+        gif::Repeat::Finite(repeats) => u32::from(repeats) + 1,
+    }
+}
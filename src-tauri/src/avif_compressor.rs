@@ -0,0 +1,126 @@
+use crate::compressor::{Capabilities, Compressor, SourceImage, compress_file};
+use crate::error::{Result, StrettaError};
+use crate::job::NamingPolicy;
+use crate::utility::{AppSettings, CompressionFailure, CompressionResult};
+use ravif::{Encoder, Img, RGBA8};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+#[cfg(feature = "gui")]
+use {
+    crate::diagnostics::log_line,
+    crate::job::BatchReport,
+    crate::utility::{CompressionMethod, load_settings, run_app_job, start_app_batch},
+    tauri::AppHandle,
+};
+
+/// AVIF output through ravif, which wraps the pure-Rust rav1e AV1 encoder.
+pub struct AvifCompressor {
+    pub options: AvifOptions,
+}
+
+/// Knobs for ravif, saved as the `avif` block of `AppSettings`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AvifOptions {
+    /// 1 to 100. Not saved with the block; it always comes from
+    /// `AppSettings::compression_quality`.
+    #[serde(skip)]
+    pub quality: f32,
+    /// rav1e preset from 1 (slowest, smallest) to 10 (fastest).
+    pub speed: u8,
+    /// Quality of the alpha channel, 1 to 100. Only used for images with
+    /// transparency.
+    pub alpha_quality: f32,
+}
+
+impl Default for AvifOptions {
+    fn default() -> Self {
+        Self {
+            quality: 75.0,
+            speed: 6,
+            alpha_quality: 90.0,
+        }
+    }
+}
+
+impl AvifOptions {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            quality: settings.compression_quality,
+            ..settings.avif.clone()
+        }
+    }
+
+    fn encoder(&self) -> Encoder {
+        // ravif panics on values outside these ranges
+        Encoder::new()
+            .with_quality(self.quality.clamp(1.0, 100.0))
+            .with_alpha_quality(self.alpha_quality.clamp(1.0, 100.0))
+            .with_speed(self.speed.clamp(1, 10))
+    }
+}
+
+impl Compressor for AvifCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            lossless: false,
+            alpha: true,
+            animation: false,
+        }
+    }
+
+    fn default_extension(&self) -> &'static str {
+        "avif"
+    }
+
+    fn accepts(&self, _input_path: &Path) -> bool {
+        // Unlike WebP, AVIF beats mozjpeg on JPEG inputs too
+        true
+    }
+
+    fn encode(&self, source: &SourceImage) -> Result<Vec<u8>> {
+        let rgba = source.image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        let pixels: Vec<RGBA8> = rgba
+            .pixels()
+            .map(|p| RGBA8::new(p[0], p[1], p[2], p[3]))
+            .collect();
+
+        let encoded = self
+            .options
+            .encoder()
+            .encode_rgba(Img::new(&pixels, width as usize, height as usize))
+            .map_err(|e| StrettaError::Encode(format!("AVIF encoding failed: {}", e)))?;
+
+        Ok(encoded.avif_file)
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn avif_compression(app: AppHandle, quality: Option<f32>) -> Result<BatchReport> {
+    log_line!("AVIF compression function called.");
+    start_app_batch(&app);
+
+    let saved = load_settings().unwrap_or_default();
+    let settings = AppSettings {
+        compression_quality: quality.unwrap_or(saved.compression_quality),
+        method: CompressionMethod::Avif,
+        ..saved
+    };
+
+    run_app_job(&app, settings)
+}
+
+pub fn compress_to_avif(
+    input_path: &Path,
+    output_dir: &Path,
+    options: AvifOptions,
+) -> Result<CompressionResult, CompressionFailure> {
+    compress_file(
+        &AvifCompressor { options },
+        input_path,
+        output_dir,
+        &NamingPolicy::default(),
+    )
+}
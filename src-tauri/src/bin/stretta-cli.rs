@@ -0,0 +1,180 @@
+// Headless entry point: runs the same compression engine as the GUI without
+// starting Tauri, so it works on machines with no display.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use compressor_tauri_lib::job::{CompressionJob, ProgressEvent};
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+
+const USAGE: &str = "\
+Usage: stretta-cli [OPTIONS] <INPUT>...
+
+Compresses image files, or every image directly inside the given directories.
+
+Options:
+  -m, --method <METHOD>    lossy, lossless, quantized_png, gif, webp_lossy,
+                           webp_lossless, avif, jpeg_xl (needs the jxl
+                           feature) or keep_format
+                           [default: webp_lossy]
+  -q, --quality <QUALITY>  Quality from 10 to 100 for lossy methods [default: 75]
+  -o, --output <DIR>       Directory compressed images are written to [default: compressed]
+  -h, --help               Print this help";
+
+struct CliArgs {
+    inputs: Vec<PathBuf>,
+    output_dir: PathBuf,
+    settings: AppSettings,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<CliArgs>, String> {
+    let mut inputs = Vec::new();
+    let mut output_dir = PathBuf::from("compressed");
+    let mut settings = AppSettings::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-m" | "--method" => {
+                let value = args.next().ok_or("Missing value for --method")?;
+                settings.method = value
+                    .parse::<CompressionMethod>()
+                    .map_err(|e| e.to_string())?;
+            }
+            "-q" | "--quality" => {
+                let value = args.next().ok_or("Missing value for --quality")?;
+                let quality: f32 = value
+                    .parse()
+                    .map_err(|_| format!("Invalid quality: {}", value))?;
+                if !(10.0..=100.0).contains(&quality) {
+                    return Err(format!(
+                        "Quality must be between 10 and 100, got {}",
+                        quality
+                    ));
+                }
+                settings.compression_quality = quality;
+            }
+            "-o" | "--output" => {
+                let value = args.next().ok_or("Missing value for --output")?;
+                output_dir = PathBuf::from(value);
+            }
+            flag if flag.starts_with('-') => return Err(format!("Unknown option: {}", flag)),
+            input => inputs.push(PathBuf::from(input)),
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err("No input files or directories given".to_string());
+    }
+
+    Ok(Some(CliArgs {
+        inputs,
+        output_dir,
+        settings,
+    }))
+}
+
+/// Expands directories into the files directly inside them.
+fn collect_input_files(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+
+    for input in inputs {
+        if input.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(input)
+                .map_err(|e| format!("Failed to read {}: {}", input.display(), e))?
+                .filter_map(|res| res.ok())
+                .map(|entry| entry.path())
+                .filter(|p| p.is_file())
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else if input.is_file() {
+            files.push(input.clone());
+        } else {
+            return Err(format!("No such file or directory: {}", input.display()));
+        }
+    }
+
+    Ok(files)
+}
+
+fn print_progress(event: ProgressEvent) {
+    match event {
+        ProgressEvent::FileDone {
+            file,
+            original_size,
+            compressed_size,
+            processed,
+            total,
+            ..
+        } => println!(
+            "[{}/{}] {}: {} bytes -> {} bytes",
+            processed, total, file, original_size, compressed_size
+        ),
+        ProgressEvent::FileFailed {
+            file,
+            error,
+            processed,
+            total,
+            ..
+        } => eprintln!("[{}/{}] {} failed: {}", processed, total, file, error),
+        ProgressEvent::FileSkipped {
+            file,
+            processed,
+            total,
+        } => eprintln!("[{}/{}] {} skipped", processed, total, file),
+        _ => {}
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let input_files = match collect_input_files(&args.inputs) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let input_count = input_files.len();
+    let job = CompressionJob::new(input_files, args.output_dir, args.settings);
+    let report = match job.run_with_progress(&print_progress) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let results = &report.completed;
+    let original_total: u64 = results.iter().map(|r| r.original_size).sum();
+    let compressed_total: u64 = results.iter().map(|r| r.compressed_size).sum();
+    println!(
+        "{} of {} images compressed, {} skipped: {} bytes -> {} bytes",
+        results.len(),
+        input_count,
+        report.skipped.len(),
+        original_total,
+        compressed_total
+    );
+
+    // Skipped inputs are ones the method doesn't handle, not errors
+    if report.failed.is_empty() && report.cancelled.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
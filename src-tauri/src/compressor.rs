@@ -0,0 +1,446 @@
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::alpha::{AlphaAction, flatten, has_transparency};
+use crate::animation::Animation;
+use crate::avif_compressor::{AvifCompressor, AvifOptions};
+use crate::diagnostics::log_line;
+use crate::error::{Result, StrettaError};
+use crate::gif_compressor::GifCompressor;
+use crate::job::{BatchControl, NamingPolicy};
+use crate::keep_format_compressor::KeepFormatCompressor;
+use crate::lossless_compressor::LosslessCompressor;
+use crate::lossy_compressor::{JpegOptions, LossyCompressor};
+use crate::utility::{
+    AppSettings, CompressionFailure, CompressionMethod, CompressionResult, FailureStage,
+};
+use crate::webp_compressor::{WebpCompressor, WebpOptions};
+
+/// What an encoder backend is able to preserve in its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub lossless: bool,
+    pub alpha: bool,
+    pub animation: bool,
+}
+
+/// An input file read from disk, with both its raw bytes and decoded pixels.
+pub struct SourceImage {
+    pub path: PathBuf,
+    pub bytes: Vec<u8>,
+    /// The first frame for animations. Already turned upright.
+    pub image: DynamicImage,
+    /// Only decoded for backends that can write animations. Already turned
+    /// upright.
+    pub animation: Option<Animation>,
+    /// EXIF orientation stored in `bytes`. Backends passing `bytes` through
+    /// have to keep it; the encoders writing `image` write no EXIF, so their
+    /// output never carries a stale one.
+    pub orientation: Orientation,
+    /// Set once `image` and `animation` no longer match `bytes`, after
+    /// resizing for example. Backends must then encode the pixels rather
+    /// than pass the original file through.
+    pub transformed: bool,
+}
+
+impl SourceImage {
+    pub fn frame_count(&self) -> usize {
+        self.animation.as_ref().map_or(1, |a| a.frames.len())
+    }
+}
+
+/// An image encoder backend. Each `CompressionMethod` maps to one of these
+/// through the `CompressorRegistry`.
+pub trait Compressor: Send + Sync {
+    fn capabilities(&self) -> Capabilities;
+
+    /// Extension used for files written by this backend.
+    fn default_extension(&self) -> &'static str;
+
+    /// Extension for the file written from `source`, for backends that keep
+    /// some inputs in their own format.
+    fn output_extension(&self, _source: &SourceImage) -> &'static str {
+        self.default_extension()
+    }
+
+    /// Whether this backend handles the given input itself.
+    fn accepts(&self, input_path: &Path) -> bool;
+
+    /// Method used for inputs this backend does not accept. `None` skips them.
+    fn fallback(&self) -> Option<CompressionMethod> {
+        None
+    }
+
+    fn decode(&self, input_path: &Path) -> Result<SourceImage> {
+        let bytes =
+            fs::read(input_path).map_err(|e| StrettaError::io("Failed to read image", e))?;
+        let decode_error = |e: image::ImageError| StrettaError::Decode(e.to_string());
+        let reader = ImageReader::new(Cursor::new(&bytes))
+            .with_guessed_format()
+            .map_err(|e| StrettaError::Decode(e.to_string()))?;
+        let format = reader.format();
+        let mut decoder = reader.into_decoder().map_err(decode_error)?;
+        // A broken EXIF block shouldn't cost the whole image
+        let mut orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+        if format == Some(ImageFormat::Png) && orientation == Orientation::NoTransforms {
+            // image doesn't read the eXIf chunk of PNGs
+            orientation = png_orientation(&bytes).unwrap_or(Orientation::NoTransforms);
+        }
+        let mut image = DynamicImage::from_decoder(decoder).map_err(decode_error)?;
+        image.apply_orientation(orientation);
+
+        let mut animation = if self.capabilities().animation {
+            Animation::decode(&bytes)?
+        } else {
+            None
+        };
+        if let Some(animation) = &mut animation {
+            animation.apply_orientation(orientation);
+        }
+
+        Ok(SourceImage {
+            path: input_path.to_path_buf(),
+            bytes,
+            image,
+            animation,
+            orientation,
+            transformed: false,
+        })
+    }
+
+    fn encode(&self, source: &SourceImage) -> Result<Vec<u8>>;
+
+    /// Like `encode`, for backends that can cut a file short once `control`
+    /// is cancelled. The rest ignore it and finish the file.
+    fn encode_cancellable(&self, source: &SourceImage, _control: &BatchControl) -> Result<Vec<u8>> {
+        self.encode(source)
+    }
+}
+
+/// Orientation stored in a PNG's eXIf chunk, which comes before the image
+/// data.
+fn png_orientation(bytes: &[u8]) -> Option<Orientation> {
+    // Past the signature, each chunk is a length, a type, the data and a CRC
+    let mut chunks = bytes.get(8..)?;
+    while chunks.len() >= 8 {
+        let length = u32::from_be_bytes(chunks[..4].try_into().ok()?) as usize;
+        let data = chunks.get(8..8usize.checked_add(length)?)?;
+        match &chunks[4..8] {
+            b"eXIf" => return exif_orientation(data),
+            b"IDAT" => return None,
+            _ => chunks = chunks.get(12 + length..)?,
+        }
+    }
+    None
+}
+
+/// Looks up the orientation entry in the first IFD of a TIFF-style EXIF block.
+fn exif_orientation(tiff: &[u8]) -> Option<Orientation> {
+    let big_endian = match tiff.get(..4)? {
+        b"MM\0*" => true,
+        b"II*\0" => false,
+        _ => return None,
+    };
+    let read = |offset: usize, len: usize| {
+        let bytes = tiff.get(offset..offset.checked_add(len)?)?;
+        let fold = |value: u32, &byte: &u8| value << 8 | u32::from(byte);
+        Some(if big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        })
+    };
+
+    let ifd = read(4, 4)? as usize;
+    (0..read(ifd, 2)? as usize).find_map(|i| {
+        let entry = ifd + 2 + i * 12;
+        // A single SHORT under tag 0x112
+        if read(entry, 2)? != 0x112 || read(entry + 2, 2)? != 3 {
+            return None;
+        }
+        Orientation::from_exif(read(entry + 8, 2)? as u8)
+    })
+}
+
+/// Builds a backend configured from the current settings.
+pub type CompressorFactory = fn(&AppSettings) -> Box<dyn Compressor>;
+
+pub struct CompressorRegistry {
+    factories: HashMap<CompressionMethod, CompressorFactory>,
+}
+
+impl CompressorRegistry {
+    pub fn new() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, method: CompressionMethod, factory: CompressorFactory) {
+        self.factories.insert(method, factory);
+    }
+
+    pub fn create(
+        &self,
+        method: CompressionMethod,
+        settings: &AppSettings,
+    ) -> Option<Box<dyn Compressor>> {
+        self.factories.get(&method).map(|factory| factory(settings))
+    }
+}
+
+impl Default for CompressorRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(CompressionMethod::Lossy, |settings| {
+            Box::new(LossyCompressor {
+                options: JpegOptions::from_settings(settings),
+            })
+        });
+        registry.register(CompressionMethod::Lossless, |settings| {
+            Box::new(LosslessCompressor {
+                options: settings.png.clone(),
+                quantize: None,
+                jpeg: settings.lossless_jpeg.clone(),
+                webp: WebpOptions::from_settings(settings, true),
+            })
+        });
+        registry.register(CompressionMethod::QuantizedPng, |settings| {
+            Box::new(LosslessCompressor {
+                options: settings.png.clone(),
+                quantize: Some(settings.quantize.clone()),
+                jpeg: settings.lossless_jpeg.clone(),
+                webp: WebpOptions::from_settings(settings, true),
+            })
+        });
+        registry.register(CompressionMethod::Gif, |settings| {
+            Box::new(GifCompressor {
+                options: settings.gif.clone(),
+            })
+        });
+        registry.register(CompressionMethod::WebpLossy, |settings| {
+            Box::new(WebpCompressor {
+                options: WebpOptions::from_settings(settings, false),
+            })
+        });
+        registry.register(CompressionMethod::WebpLossless, |settings| {
+            Box::new(WebpCompressor {
+                options: WebpOptions::from_settings(settings, true),
+            })
+        });
+        registry.register(CompressionMethod::Avif, |settings| {
+            Box::new(AvifCompressor {
+                options: AvifOptions::from_settings(settings),
+            })
+        });
+        registry.register(CompressionMethod::KeepFormat, |settings| {
+            Box::new(KeepFormatCompressor::from_settings(settings))
+        });
+        #[cfg(feature = "jxl")]
+        registry.register(CompressionMethod::JpegXl, |settings| {
+            Box::new(crate::jxl_compressor::JxlCompressor {
+                options: crate::jxl_compressor::JxlOptions::from_settings(settings),
+            })
+        });
+        registry
+    }
+}
+
+static REGISTRY: OnceLock<CompressorRegistry> = OnceLock::new();
+
+pub fn registry() -> &'static CompressorRegistry {
+    REGISTRY.get_or_init(CompressorRegistry::default)
+}
+
+/// Decodes, encodes and writes a single file with the given backend, using
+/// the default resize and transparency settings: no resizing, and transparent
+/// inputs flattened onto white for backends that can't keep alpha.
+pub fn compress_file(
+    backend: &dyn Compressor,
+    input_path: &Path,
+    output_dir: &Path,
+    naming: &NamingPolicy,
+) -> Result<CompressionResult, CompressionFailure> {
+    compress_file_with(
+        backend,
+        input_path,
+        output_dir,
+        naming,
+        &AppSettings::default(),
+        None,
+        &BatchControl::default(),
+    )
+}
+
+/// Like `compress_file`, resizing as `settings.resize` says and handling
+/// transparent inputs for backends that can't keep alpha as `settings.alpha`
+/// says. `reroute` is the backend used for `AlphaAction::Reroute`, or its
+/// fallback for inputs it doesn't accept; without either those inputs are
+/// flattened. `control` lets backends that can stop partway through an
+/// encode do so when the batch is cancelled.
+pub fn compress_file_with(
+    backend: &dyn Compressor,
+    input_path: &Path,
+    output_dir: &Path,
+    naming: &NamingPolicy,
+    settings: &AppSettings,
+    reroute: Option<&dyn Compressor>,
+    control: &BatchControl,
+) -> Result<CompressionResult, CompressionFailure> {
+    let failure = |stage| {
+        move |error: StrettaError| CompressionFailure::new(input_path, stage, error.to_string())
+    };
+    let alpha = &settings.alpha;
+
+    let mut source = backend
+        .decode(input_path)
+        .map_err(failure(FailureStage::Decode))?;
+
+    // The reroute backend is picked per file the same way the job picks the
+    // main one, and only counts if whatever it lands on keeps the alpha
+    let reroute_fallback;
+    let reroute = match reroute {
+        Some(reroute) if reroute.accepts(input_path) => Some(reroute),
+        Some(reroute) => {
+            reroute_fallback = reroute
+                .fallback()
+                .and_then(|method| registry().create(method, settings))
+                .filter(|fallback| fallback.capabilities().alpha);
+            reroute_fallback.as_deref()
+        }
+        None => None,
+    };
+
+    let alpha_action = if backend.capabilities().alpha || !has_transparency(&source.image) {
+        None
+    } else if alpha.action == AlphaAction::Reroute && reroute.is_none() {
+        Some(AlphaAction::Flatten)
+    } else {
+        Some(alpha.action)
+    };
+    let (encoded, extension) = match (alpha_action, reroute) {
+        (Some(AlphaAction::Reroute), Some(reroute)) => {
+            log_line!(
+                "{} has transparency, compressing it with the reroute method instead",
+                input_path.display()
+            );
+            let mut result = compress_file_with(
+                reroute, input_path, output_dir, naming, settings, None, control,
+            )?;
+            result.alpha_action = alpha_action;
+            return Ok(result);
+        }
+        (Some(AlphaAction::KeepOriginal), _) => {
+            log_line!(
+                "{} has transparency, keeping the original",
+                input_path.display()
+            );
+            let extension = input_path
+                .extension()
+                .and_then(|s| s.to_str())
+                .unwrap_or(backend.default_extension());
+            (source.bytes.clone(), extension)
+        }
+        _ => {
+            if settings.resize.apply(&mut source) {
+                log_line!(
+                    "Resized {} to {}x{}",
+                    input_path.display(),
+                    source.image.width(),
+                    source.image.height()
+                );
+            }
+            if alpha_action.is_some() {
+                source.image = flatten(&source.image, alpha.background);
+                source.transformed = true;
+            }
+            let encoded = backend
+                .encode_cancellable(&source, control)
+                .map_err(failure(FailureStage::Encode))?;
+            (encoded, backend.output_extension(&source))
+        }
+    };
+
+    let output_path = naming.output_path(input_path, output_dir, extension);
+
+    write_output(&output_path, &encoded).map_err(failure(FailureStage::Write))?;
+
+    Ok(build_result(
+        input_path,
+        &output_path,
+        source.bytes.len() as u64,
+        encoded.len() as u64,
+        source.frame_count() as u32,
+        (source.image.width(), source.image.height()),
+        alpha_action,
+    ))
+}
+
+/// Writes through a temporary file so an interrupted or failed write never
+/// leaves a truncated image behind under the final name.
+pub fn write_output(output_path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut partial_name = output_path.file_name().unwrap_or_default().to_os_string();
+    partial_name.push(".part");
+    let partial_path = output_path.with_file_name(partial_name);
+
+    let written =
+        fs::write(&partial_path, bytes).and_then(|_| fs::rename(&partial_path, output_path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&partial_path);
+        return Err(StrettaError::io("Write output failed", e));
+    }
+
+    Ok(())
+}
+
+/// Percentage saved going from `original_size` to `compressed_size`.
+/// Negative when the output grew.
+pub fn reduction_percent(original_size: u64, compressed_size: u64) -> f32 {
+    if original_size == 0 {
+        0.0
+    } else if compressed_size <= original_size {
+        100.0 * (original_size - compressed_size) as f32 / original_size as f32
+    } else {
+        -100.0 * (compressed_size - original_size) as f32 / original_size as f32
+    }
+}
+
+pub fn build_result(
+    input_path: &Path,
+    output_path: &Path,
+    original_size: u64,
+    compressed_size: u64,
+    frame_count: u32,
+    (width, height): (u32, u32),
+    alpha_action: Option<AlphaAction>,
+) -> CompressionResult {
+    let reduction_percent = reduction_percent(original_size, compressed_size);
+
+    log_line!(
+        "Compressed {} to {}: {} bytes -> {} bytes, {:.2}% reduction",
+        input_path.display(),
+        output_path.display(),
+        original_size,
+        compressed_size,
+        reduction_percent
+    );
+
+    CompressionResult {
+        original_path: input_path.display().to_string(),
+        compressed_path: output_path.display().to_string(),
+        original_size,
+        compressed_size,
+        reduction_percent,
+        frame_count,
+        width,
+        height,
+        alpha_action,
+        original_base64: None,
+        compressed_base64: None,
+    }
+}
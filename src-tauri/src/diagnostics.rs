@@ -0,0 +1,159 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Mutex;
+#[cfg(feature = "gui")]
+use tauri::AppHandle;
+
+use crate::error::Result;
+use crate::utility::{
+    AppSettings, get_input_path, get_output_path, get_settings_dir, load_settings,
+};
+
+/// How many log lines `get_diagnostics` can report.
+const RECENT_LOG_CAPACITY: usize = 200;
+
+static RECENT_LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// `println!` that also keeps the line around for bug reports.
+macro_rules! log_line {
+    ($($arg:tt)*) => {
+        $crate::diagnostics::record(format!($($arg)*))
+    };
+}
+pub(crate) use log_line;
+
+pub fn record(line: String) {
+    println!("{}", line);
+
+    let mut log = RECENT_LOG.lock().unwrap_or_else(|e| e.into_inner());
+    if log.len() == RECENT_LOG_CAPACITY {
+        log.pop_front();
+    }
+    log.push_back(line);
+}
+
+fn recent_log() -> Vec<String> {
+    let log = RECENT_LOG.lock().unwrap_or_else(|e| e.into_inner());
+    log.iter().cloned().collect()
+}
+
+/// Everything worth attaching to a bug report.
+#[derive(Serialize, Debug)]
+pub struct Diagnostics {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub thread_pool_size: usize,
+    /// Optional Cargo features this build has, e.g. `jxl`.
+    pub features: Vec<&'static str>,
+    pub encoders: EncoderVersions,
+    pub directories: Vec<DirectoryInfo>,
+    /// `None` when the saved settings couldn't be read.
+    pub settings: Option<AppSettings>,
+    pub recent_log: Vec<String>,
+}
+
+/// libwebp and libjxl report their own versions. mozjpeg is built from the
+/// source vendored in `mozjpeg-sys` and only reports the libjpeg API level it
+/// implements, and oxipng and ravif are Rust crates, so for those the crate
+/// versions resolved at build time are reported instead, see `build.rs`.
+#[derive(Serialize, Debug)]
+pub struct EncoderVersions {
+    pub libwebp: String,
+    /// `None` when built without the `jxl` feature.
+    pub libjxl: Option<String>,
+    pub libjpeg_api: String,
+    pub mozjpeg_sys_crate: &'static str,
+    pub oxipng_crate: &'static str,
+    pub ravif_crate: &'static str,
+}
+
+impl EncoderVersions {
+    fn current() -> Self {
+        // Packed as 0xMMmmpp
+        let webp = unsafe { libwebp_sys::WebPGetEncoderVersion() };
+        let jpeg = mozjpeg_sys::JPEG_LIB_VERSION;
+
+        Self {
+            libwebp: format!("{}.{}.{}", webp >> 16, (webp >> 8) & 0xFF, webp & 0xFF),
+            libjxl: libjxl_version(),
+            libjpeg_api: format!("{}.{}", jpeg / 10, jpeg % 10),
+            mozjpeg_sys_crate: env!("STRETTA_MOZJPEG_SYS_VERSION"),
+            oxipng_crate: env!("STRETTA_OXIPNG_VERSION"),
+            ravif_crate: env!("STRETTA_RAVIF_VERSION"),
+        }
+    }
+}
+
+#[cfg(feature = "jxl")]
+fn libjxl_version() -> Option<String> {
+    // Packed as major * 1000000 + minor * 1000 + patch
+    let version = unsafe { jpegxl_sys::encoder::encode::JxlEncoderVersion() };
+    Some(format!(
+        "{}.{}.{}",
+        version / 1_000_000,
+        version / 1000 % 1000,
+        version % 1000
+    ))
+}
+
+#[cfg(not(feature = "jxl"))]
+fn libjxl_version() -> Option<String> {
+    None
+}
+
+fn enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "jxl") {
+        features.push("jxl");
+    }
+    features
+}
+
+#[derive(Serialize, Debug)]
+pub struct DirectoryInfo {
+    pub name: &'static str,
+    pub path: Option<String>,
+    /// `None` when the directory isn't set up or its filesystem can't be queried.
+    pub free_bytes: Option<u64>,
+}
+
+impl DirectoryInfo {
+    fn new(name: &'static str, path: Result<&Path>) -> Self {
+        let path = path.ok();
+        Self {
+            name,
+            path: path.map(|p| p.display().to_string()),
+            free_bytes: path.and_then(|p| fs4::available_space(p).ok()),
+        }
+    }
+}
+
+impl Diagnostics {
+    /// Gathers the report; `app_version` comes from the caller since the
+    /// desktop app reads it from its Tauri config.
+    pub fn collect(app_version: String) -> Self {
+        Self {
+            app_version,
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            thread_pool_size: rayon::current_num_threads(),
+            features: enabled_features(),
+            encoders: EncoderVersions::current(),
+            directories: vec![
+                DirectoryInfo::new("input", get_input_path().map(|p| p.as_path())),
+                DirectoryInfo::new("output", get_output_path().map(|p| p.as_path())),
+                DirectoryInfo::new("settings", get_settings_dir().map(|p| p.as_path())),
+            ],
+            settings: load_settings().ok(),
+            recent_log: recent_log(),
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn get_diagnostics(app: AppHandle) -> Diagnostics {
+    Diagnostics::collect(app.package_info().version.to_string())
+}
@@ -0,0 +1,52 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+/// Errors surfaced by the backend. Serialises to `{ kind, message }` so the
+/// frontend can tell what went wrong without parsing strings.
+#[derive(Debug, thiserror::Error)]
+pub enum StrettaError {
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to decode image: {0}")]
+    Decode(String),
+    #[error("Failed to encode image: {0}")]
+    Encode(String),
+    #[error("Invalid settings: {0}")]
+    Settings(String),
+    #[error("{0}")]
+    Validation(String),
+}
+
+pub type Result<T, E = StrettaError> = std::result::Result<T, E>;
+
+impl StrettaError {
+    pub fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Io { .. } => "io",
+            Self::Decode(_) => "decode",
+            Self::Encode(_) => "encode",
+            Self::Settings(_) => "settings",
+            Self::Validation(_) => "validation",
+        }
+    }
+}
+
+impl Serialize for StrettaError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("StrettaError", 2)?;
+        error.serialize_field("kind", self.kind())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
@@ -0,0 +1,503 @@
+use crate::compressor::{Capabilities, Compressor, SourceImage, compress_file};
+use crate::diagnostics::log_line;
+use crate::error::{Result, StrettaError};
+use crate::job::NamingPolicy;
+use crate::utility::{CompressionFailure, CompressionResult};
+use color_quant::NeuQuant;
+use gif::{DisposalMethod, Encoder, EncodingError, Frame, Repeat};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+#[cfg(feature = "gui")]
+use {
+    crate::job::BatchReport,
+    crate::utility::{AppSettings, CompressionMethod, load_settings, run_app_job, start_app_batch},
+    tauri::AppHandle,
+};
+
+/// GIF output that keeps every frame and its timing. Other inputs are
+/// skipped.
+pub struct GifCompressor {
+    pub options: GifOptions,
+}
+
+/// Knobs for the GIF optimiser, saved as the `gif` block of `AppSettings`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GifOptions {
+    /// Most colours per palette, 2 to 256.
+    pub colors: u16,
+    /// How far from the exact colour the LZW encoder may stray to reuse a
+    /// longer run, 0 (off) to 100. Like gifsicle's `--lossy`.
+    pub lossy: u8,
+    /// Only store the part of each frame that changed.
+    pub crop_frames: bool,
+    /// Make pixels that didn't change transparent, which compresses better.
+    pub transparency: bool,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            colors: 256,
+            lossy: 0,
+            crop_frames: true,
+            transparency: true,
+        }
+    }
+}
+
+/// GIF has 1-bit transparency, so `None` is a transparent pixel.
+type Pixel = Option<[u8; 3]>;
+
+fn pixel(rgba: &image::Rgba<u8>) -> Pixel {
+    (rgba[3] >= 128).then_some([rgba[0], rgba[1], rgba[2]])
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+}
+
+/// A frame cut down to what has to be drawn, before palettes are picked.
+struct PlannedFrame {
+    rect: Rect,
+    pixels: Vec<Pixel>,
+    /// Hundredths of a second.
+    delay: u16,
+    dispose: DisposalMethod,
+}
+
+/// The colours one frame is written with.
+struct Palette {
+    colors: Vec<[u8; 3]>,
+    transparent: Option<u8>,
+    /// Exact lookup, or a quantiser when the frame has too many colours.
+    lookup: Result<HashMap<[u8; 3], u8>, NeuQuant>,
+}
+
+impl Palette {
+    fn exact(colors: HashSet<[u8; 3]>, transparent: bool) -> Self {
+        // Sorted so the same input always gives the same file
+        let mut colors: Vec<[u8; 3]> = colors.into_iter().collect();
+        colors.sort_unstable();
+        let lookup = colors
+            .iter()
+            .enumerate()
+            .map(|(index, &color)| (color, index as u8))
+            .collect();
+        Self {
+            transparent: transparent.then_some(colors.len() as u8),
+            colors,
+            lookup: Ok(lookup),
+        }
+    }
+
+    fn quantized(pixels: &[Pixel], max_colors: usize, transparent: bool) -> Self {
+        let rgba: Vec<u8> = pixels
+            .iter()
+            .flatten()
+            .flat_map(|&[r, g, b]| [r, g, b, 255])
+            .collect();
+        let quantizer = NeuQuant::new(10, max_colors, &rgba);
+        let colors = quantizer
+            .color_map_rgb()
+            .chunks_exact(3)
+            .map(|c| [c[0], c[1], c[2]])
+            .collect::<Vec<_>>();
+        Self {
+            transparent: transparent.then_some(colors.len() as u8),
+            colors,
+            lookup: Err(quantizer),
+        }
+    }
+
+    fn index_of(&self, pixel: Pixel) -> u8 {
+        match (pixel, &self.lookup) {
+            (None, _) => self.transparent.unwrap_or(0),
+            (Some(color), Ok(lookup)) => lookup[&color],
+            (Some([r, g, b]), Err(quantizer)) => quantizer.index_of(&[r, g, b, 255]) as u8,
+        }
+    }
+
+    fn flattened(&self) -> Vec<u8> {
+        let mut flat: Vec<u8> = self.colors.iter().flatten().copied().collect();
+        if self.transparent.is_some() {
+            flat.extend([0, 0, 0]);
+        }
+        flat
+    }
+}
+
+impl GifCompressor {
+    fn max_colors(&self) -> usize {
+        usize::from(self.options.colors.clamp(2, 256))
+    }
+
+    /// Works out what each frame has to draw. Frames are drawn on top of the
+    /// previous one, except where a pixel turns transparent: the previous
+    /// frame then clears the canvas once it's done.
+    fn plan(&self, frames: &[(RgbaImage, u16)]) -> Vec<PlannedFrame> {
+        let (width, height) = frames[0].0.dimensions();
+        let full = Rect {
+            left: 0,
+            top: 0,
+            width,
+            height,
+        };
+        let canvases: Vec<Vec<Pixel>> = frames
+            .iter()
+            .map(|(image, _)| image.pixels().map(pixel).collect())
+            .collect();
+        let needs_clear: Vec<bool> = (0..canvases.len())
+            .map(|i| {
+                i > 0
+                    && canvases[i]
+                        .iter()
+                        .zip(&canvases[i - 1])
+                        .any(|(now, before)| now.is_none() && before.is_some())
+            })
+            .collect();
+
+        (0..canvases.len())
+            .map(|i| {
+                let canvas = &canvases[i];
+                let previous = (i > 0 && !needs_clear[i]).then(|| &canvases[i - 1]);
+                let clears = needs_clear.get(i + 1).copied().unwrap_or(false);
+                let changed = |at: usize| match previous {
+                    Some(previous) => previous[at] != canvas[at],
+                    None => canvas[at].is_some(),
+                };
+
+                // A frame that clears the canvas afterwards has to cover all of it
+                let rect = if self.options.crop_frames && !clears {
+                    changed_bounds(width, height, changed).unwrap_or(Rect {
+                        left: 0,
+                        top: 0,
+                        width: 1,
+                        height: 1,
+                    })
+                } else {
+                    full
+                };
+
+                let mut pixels = Vec::with_capacity((rect.width * rect.height) as usize);
+                for y in rect.top..rect.top + rect.height {
+                    for x in rect.left..rect.left + rect.width {
+                        let at = (y * width + x) as usize;
+                        let keep_previous =
+                            self.options.transparency && previous.is_some() && !changed(at);
+                        pixels.push(if keep_previous { None } else { canvas[at] });
+                    }
+                }
+
+                PlannedFrame {
+                    rect,
+                    pixels,
+                    delay: frames[i].1,
+                    dispose: if clears {
+                        DisposalMethod::Background
+                    } else {
+                        DisposalMethod::Keep
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// One shared palette when every frame fits in it, otherwise one per
+    /// frame.
+    fn palettes(&self, planned: &[PlannedFrame]) -> (Option<Palette>, Vec<Option<Palette>>) {
+        let max_colors = self.max_colors();
+        let transparent = planned.iter().any(|f| f.pixels.contains(&None));
+        let all_colors: HashSet<[u8; 3]> = planned
+            .iter()
+            .flat_map(|f| f.pixels.iter().flatten().copied())
+            .collect();
+
+        if all_colors.len() + usize::from(transparent) <= max_colors {
+            let global = Palette::exact(all_colors, transparent);
+            return (Some(global), planned.iter().map(|_| None).collect());
+        }
+
+        let locals = planned
+            .iter()
+            .map(|frame| {
+                let transparent = frame.pixels.contains(&None);
+                let colors: HashSet<[u8; 3]> = frame.pixels.iter().flatten().copied().collect();
+                let room = max_colors - usize::from(transparent);
+                Some(if colors.len() <= room {
+                    Palette::exact(colors, transparent)
+                } else {
+                    Palette::quantized(&frame.pixels, room, transparent)
+                })
+            })
+            .collect();
+        (None, locals)
+    }
+
+    fn encode_frames(&self, frames: &[(RgbaImage, u16)], loop_count: u32) -> Result<Vec<u8>> {
+        let (width, height) = frames[0].0.dimensions();
+        let dimension = |value: u32| {
+            u16::try_from(value).map_err(|_| {
+                StrettaError::Encode(format!("{}x{} is too large for GIF", width, height))
+            })
+        };
+        let (width, height) = (dimension(width)?, dimension(height)?);
+
+        let planned = self.plan(frames);
+        let (global, locals) = self.palettes(&planned);
+
+        let global_colors = global.as_ref().map(Palette::flattened).unwrap_or_default();
+        let mut encoder =
+            Encoder::new(Vec::new(), width, height, &global_colors).map_err(encode_error)?;
+        if frames.len() > 1 {
+            let repeat = match loop_count {
+                0 => Repeat::Infinite,
+                plays => Repeat::Finite(u16::try_from(plays - 1).unwrap_or(u16::MAX)),
+            };
+            encoder.set_repeat(repeat).map_err(encode_error)?;
+        }
+
+        for (frame, local) in planned.iter().zip(&locals) {
+            let palette = local
+                .as_ref()
+                .or(global.as_ref())
+                .expect("every frame has a global or a local palette");
+            let indices: Vec<u8> = frame.pixels.iter().map(|&p| palette.index_of(p)).collect();
+
+            let mut gif_frame = Frame {
+                left: frame.rect.left as u16,
+                top: frame.rect.top as u16,
+                width: frame.rect.width as u16,
+                height: frame.rect.height as u16,
+                delay: frame.delay,
+                dispose: frame.dispose,
+                transparent: palette.transparent,
+                palette: local.as_ref().map(Palette::flattened),
+                ..Frame::default()
+            };
+
+            if self.options.lossy > 0 {
+                gif_frame.buffer =
+                    Cow::Owned(lzw_encode_lossy(&indices, palette, self.options.lossy));
+                encoder
+                    .write_lzw_pre_encoded_frame(&gif_frame)
+                    .map_err(encode_error)?;
+            } else {
+                gif_frame.buffer = Cow::Owned(indices);
+                encoder.write_frame(&gif_frame).map_err(encode_error)?;
+            }
+        }
+
+        encoder
+            .into_inner()
+            .map_err(|e| StrettaError::Encode(format!("GIF encoding failed: {}", e)))
+    }
+}
+
+fn encode_error(error: EncodingError) -> StrettaError {
+    StrettaError::Encode(format!("GIF encoding failed: {}", error))
+}
+
+/// Smallest rectangle holding every pixel `changed` is true for.
+fn changed_bounds(width: u32, height: u32, changed: impl Fn(usize) -> bool) -> Option<Rect> {
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+    for y in 0..height {
+        for x in 0..width {
+            if changed((y * width + x) as usize) {
+                left = left.min(x);
+                top = top.min(y);
+                right = right.max(x);
+                bottom = bottom.max(y);
+            }
+        }
+    }
+
+    (left <= right).then(|| Rect {
+        left,
+        top,
+        width: right - left + 1,
+        height: bottom - top + 1,
+    })
+}
+
+/// LZW where a dictionary entry also matches a pixel whose colour is close
+/// enough to the entry's last colour, so runs get longer at the cost of
+/// small colour shifts. Returns the minimum code size followed by the packed
+/// codes, as `Encoder::write_lzw_pre_encoded_frame` expects.
+fn lzw_encode_lossy(indices: &[u8], palette: &Palette, lossy: u8) -> Vec<u8> {
+    const MAX_CODES: u16 = 4096;
+
+    let symbols = palette.colors.len() + usize::from(palette.transparent.is_some());
+    let min_code_size = symbols.max(4).next_power_of_two().trailing_zeros() as u8;
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let tolerance = (f32::from(lossy.min(100)) * 0.6).powi(2);
+
+    let is_close = |wanted: u8, candidate: u8| {
+        if Some(wanted) == palette.transparent || Some(candidate) == palette.transparent {
+            return false;
+        }
+        let (a, b) = (
+            palette.colors[wanted as usize],
+            palette.colors[candidate as usize],
+        );
+        let distance: f32 = a
+            .iter()
+            .zip(b)
+            .map(|(&a, b)| (f32::from(a) - f32::from(b)).powi(2))
+            .sum();
+        distance <= tolerance
+    };
+
+    let mut output = vec![min_code_size];
+    let mut bits = BitWriter::default();
+    let mut children: Vec<Vec<(u8, u16)>> = vec![Vec::new(); MAX_CODES as usize];
+    let mut next_code = end + 1;
+    let mut code_size = min_code_size + 1;
+    bits.write(&mut output, clear, code_size);
+
+    let Some((&first, rest)) = indices.split_first() else {
+        bits.write(&mut output, end, code_size);
+        bits.finish(&mut output);
+        return output;
+    };
+
+    let mut node = u16::from(first);
+    for &index in rest {
+        let node_children = &children[node as usize];
+        let exact = node_children.iter().find(|&&(symbol, _)| symbol == index);
+        let close = || {
+            node_children
+                .iter()
+                .find(|&&(symbol, _)| is_close(index, symbol))
+        };
+        if let Some(&(_, child)) = exact.or_else(close) {
+            node = child;
+            continue;
+        }
+
+        bits.write(&mut output, node, code_size);
+        if next_code < MAX_CODES {
+            if next_code == 1 << code_size {
+                code_size += 1;
+            }
+            children[node as usize].push((index, next_code));
+            next_code += 1;
+        } else {
+            bits.write(&mut output, clear, code_size);
+            children.iter_mut().for_each(Vec::clear);
+            next_code = end + 1;
+            code_size = min_code_size + 1;
+        }
+        node = u16::from(index);
+    }
+
+    bits.write(&mut output, node, code_size);
+    bits.write(&mut output, end, code_size);
+    bits.finish(&mut output);
+    output
+}
+
+/// Packs codes least significant bit first, as GIF wants them.
+#[derive(Default)]
+struct BitWriter {
+    buffer: u32,
+    count: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, output: &mut Vec<u8>, code: u16, size: u8) {
+        self.buffer |= u32::from(code) << self.count;
+        self.count += size;
+        while self.count >= 8 {
+            output.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(&mut self, output: &mut Vec<u8>) {
+        if self.count > 0 {
+            output.push(self.buffer as u8);
+        }
+        *self = Self::default();
+    }
+}
+
+impl Compressor for GifCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            lossless: self.options.lossy == 0 && self.options.colors >= 256,
+            alpha: true,
+            animation: true,
+        }
+    }
+
+    fn default_extension(&self) -> &'static str {
+        "gif"
+    }
+
+    fn accepts(&self, input_path: &Path) -> bool {
+        input_path
+            .extension()
+            .and_then(|s| s.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"))
+    }
+
+    fn encode(&self, source: &SourceImage) -> Result<Vec<u8>> {
+        let Some(animation) = &source.animation else {
+            return self.encode_frames(&[(source.image.to_rgba8(), 0)], 1);
+        };
+
+        let frames: Vec<(RgbaImage, u16)> = animation
+            .frames
+            .iter()
+            .map(|frame| {
+                let (numerator, denominator) = frame.delay().numer_denom_ms();
+                let centiseconds = f64::from(numerator) / f64::from(denominator.max(1)) / 10.0;
+                (frame.buffer().clone(), centiseconds.round() as u16)
+            })
+            .collect();
+
+        log_line!(
+            "Optimising {} frames of {}",
+            frames.len(),
+            source.path.display()
+        );
+        self.encode_frames(&frames, animation.loop_count)
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn gif_compression(app: AppHandle) -> Result<BatchReport> {
+    log_line!("GIF compression function called.");
+    start_app_batch(&app);
+
+    let settings = AppSettings {
+        method: CompressionMethod::Gif,
+        ..load_settings().unwrap_or_default()
+    };
+
+    run_app_job(&app, settings)
+}
+
+pub fn compress_gif(
+    input_path: &Path,
+    output_dir: &Path,
+    options: GifOptions,
+) -> Result<CompressionResult, CompressionFailure> {
+    compress_file(
+        &GifCompressor { options },
+        input_path,
+        output_dir,
+        &NamingPolicy::default(),
+    )
+}
@@ -0,0 +1,378 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+
+use crate::alpha::AlphaAction;
+use crate::compressor::{Compressor, compress_file_with, registry};
+use crate::diagnostics::log_line;
+use crate::error::{Result, StrettaError};
+use crate::utility::{
+    AppSettings, CompressionFailure, CompressionMethod, CompressionResult, FailureStage,
+    deduplicate_path,
+};
+
+/// How output files are named inside a job's output directory.
+#[derive(Debug, Clone)]
+pub struct NamingPolicy {
+    /// Appended to the input's file stem, e.g. `photo` -> `photo_compressed.jpg`.
+    pub suffix: String,
+    /// Replace an existing file instead of appending `_1`, `_2`, etc.
+    pub overwrite: bool,
+}
+
+impl Default for NamingPolicy {
+    fn default() -> Self {
+        Self {
+            suffix: "_compressed".to_string(),
+            overwrite: false,
+        }
+    }
+}
+
+impl NamingPolicy {
+    pub fn output_path(&self, input_path: &Path, output_dir: &Path, extension: &str) -> PathBuf {
+        let file_stem = input_path.file_stem().unwrap_or_default().to_string_lossy();
+        let initial_path = output_dir.join(format!("{}{}.{}", file_stem, self.suffix, extension));
+
+        if self.overwrite {
+            initial_path
+        } else {
+            deduplicate_path(&initial_path)
+        }
+    }
+}
+
+/// Shared switch a running batch checks between files. Files already being
+/// encoded are finished, cut short where the encoder allows: oxipng skips its
+/// trials if the cancel came before it started, and the Zopfli pass if it
+/// came before that, keeping the libdeflate result.
+#[derive(Debug, Default)]
+pub struct BatchControl {
+    cancelled: AtomicBool,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+}
+
+impl BatchControl {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        // Wake paused workers so they can see the cancellation
+        self.resume();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn pause(&self) {
+        *self.paused.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    }
+
+    pub fn resume(&self) {
+        *self.paused.lock().unwrap_or_else(|e| e.into_inner()) = false;
+        self.resumed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Clears a previous cancel or pause before a new batch starts.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+        self.resume();
+    }
+
+    /// Blocks while the batch is paused. Returns `false` once it's cancelled.
+    pub fn checkpoint(&self) -> bool {
+        let paused = self.paused.lock().unwrap_or_else(|e| e.into_inner());
+        let _paused = self
+            .resumed
+            .wait_while(paused, |paused| *paused && !self.is_cancelled())
+            .unwrap_or_else(|e| e.into_inner());
+        !self.is_cancelled()
+    }
+}
+
+/// Everything the pipeline needs to compress a batch of images. Nothing here
+/// depends on Tauri or the app's global paths, so jobs can be built by the GUI
+/// commands, the CLI or tests alike.
+#[derive(Debug, Clone)]
+pub struct CompressionJob {
+    pub inputs: Vec<PathBuf>,
+    pub output_dir: PathBuf,
+    pub settings: AppSettings,
+    pub naming: NamingPolicy,
+    pub control: Arc<BatchControl>,
+}
+
+/// What happened to each input of a batch.
+#[derive(Serialize, Debug, Default)]
+pub struct BatchReport {
+    pub completed: Vec<CompressionResult>,
+    /// Inputs no backend for the method accepts.
+    pub skipped: Vec<String>,
+    /// Inputs that were never started because the batch was cancelled.
+    pub cancelled: Vec<String>,
+    pub failed: Vec<CompressionFailure>,
+}
+
+impl BatchReport {
+    pub fn is_empty(&self) -> bool {
+        self.completed.is_empty()
+            && self.skipped.is_empty()
+            && self.cancelled.is_empty()
+            && self.failed.is_empty()
+    }
+}
+
+enum FileOutcome {
+    Completed(CompressionResult),
+    Skipped(String),
+    Cancelled(String),
+    Failed(CompressionFailure),
+}
+
+impl CompressionJob {
+    pub fn new(inputs: Vec<PathBuf>, output_dir: PathBuf, settings: AppSettings) -> Self {
+        Self {
+            inputs,
+            output_dir,
+            settings,
+            naming: NamingPolicy::default(),
+            control: Arc::new(BatchControl::default()),
+        }
+    }
+
+    /// Builds a job over every file directly inside `input_dir`.
+    pub fn from_directory(
+        input_dir: &Path,
+        output_dir: PathBuf,
+        settings: AppSettings,
+    ) -> Result<Self> {
+        let mut inputs: Vec<PathBuf> = fs::read_dir(input_dir)
+            .map_err(|e| StrettaError::io("Failed to read input dir", e))?
+            .filter_map(|res| res.ok())
+            .map(|entry| entry.path())
+            .filter(|p| p.is_file())
+            .collect();
+        inputs.sort();
+
+        Ok(Self::new(inputs, output_dir, settings))
+    }
+
+    /// Compresses every input using the backend registered for the job's
+    /// method, falling back per file where that backend declines one.
+    pub fn run(&self) -> Result<BatchReport> {
+        self.run_with_progress(&|_| {})
+    }
+
+    /// Like `run`, reporting each step of the batch to `on_progress` as it
+    /// happens. Called from the worker threads, so events for different files
+    /// can arrive in any order.
+    pub fn run_with_progress(
+        &self,
+        on_progress: &(dyn Fn(ProgressEvent) + Sync),
+    ) -> Result<BatchReport> {
+        let settings = &self.settings;
+        let backend = registry()
+            .create(settings.method, settings)
+            .ok_or_else(|| {
+                StrettaError::Settings(format!(
+                    "No compressor registered for {}",
+                    settings.method.as_str()
+                ))
+            })?;
+        settings.resize.validate()?;
+        let fallback = backend
+            .fallback()
+            .and_then(|method| registry().create(method, settings));
+        let reroute = match settings.alpha.action {
+            AlphaAction::Reroute => Some(self.reroute_backend()?),
+            _ => None,
+        };
+        // Only the quantiser reads these, so other methods don't trip over them
+        let quantizing = settings.method == CompressionMethod::QuantizedPng
+            || (reroute.is_some()
+                && settings.alpha.reroute_method == CompressionMethod::QuantizedPng);
+        if quantizing {
+            settings.quantize.validate()?;
+        }
+
+        fs::create_dir_all(&self.output_dir)
+            .map_err(|e| StrettaError::io("Failed to create output dir", e))?;
+
+        let total = self.inputs.len();
+        let processed = AtomicUsize::new(0);
+        let batch_start = Instant::now();
+        on_progress(ProgressEvent::BatchStarted { total });
+
+        let outcomes: Vec<FileOutcome> = self
+            .inputs
+            .par_iter()
+            .map(|input| {
+                let file = input.display().to_string();
+                if !self.control.checkpoint() {
+                    on_progress(ProgressEvent::FileCancelled {
+                        file: file.clone(),
+                        processed: processed.fetch_add(1, Ordering::SeqCst) + 1,
+                        total,
+                    });
+                    return FileOutcome::Cancelled(file);
+                }
+
+                let backend = if backend.accepts(input) {
+                    Some(backend.as_ref())
+                } else {
+                    fallback.as_deref()
+                };
+                let Some(backend) = backend else {
+                    on_progress(ProgressEvent::FileSkipped {
+                        file: file.clone(),
+                        processed: processed.fetch_add(1, Ordering::SeqCst) + 1,
+                        total,
+                    });
+                    return FileOutcome::Skipped(file);
+                };
+
+                on_progress(ProgressEvent::FileStarted { file: file.clone() });
+                let file_start = Instant::now();
+                let result = compress_file_with(
+                    backend,
+                    input,
+                    &self.output_dir,
+                    &self.naming,
+                    settings,
+                    reroute.as_deref(),
+                    &self.control,
+                );
+                let elapsed_ms = file_start.elapsed().as_millis() as u64;
+                let processed = processed.fetch_add(1, Ordering::SeqCst) + 1;
+
+                match result {
+                    Ok(result) => {
+                        on_progress(ProgressEvent::FileDone {
+                            file,
+                            original_size: result.original_size,
+                            compressed_size: result.compressed_size,
+                            elapsed_ms,
+                            processed,
+                            total,
+                        });
+                        FileOutcome::Completed(result)
+                    }
+                    Err(failure) => {
+                        on_progress(ProgressEvent::FileFailed {
+                            file,
+                            stage: failure.stage,
+                            error: failure.error.clone(),
+                            elapsed_ms,
+                            processed,
+                            total,
+                        });
+                        FileOutcome::Failed(failure)
+                    }
+                }
+            })
+            .collect();
+
+        let mut report = BatchReport::default();
+        for outcome in outcomes {
+            match outcome {
+                FileOutcome::Completed(result) => report.completed.push(result),
+                FileOutcome::Skipped(file) => report.skipped.push(file),
+                FileOutcome::Cancelled(file) => report.cancelled.push(file),
+                FileOutcome::Failed(failure) => report.failed.push(failure),
+            }
+        }
+
+        on_progress(ProgressEvent::BatchFinished {
+            completed: report.completed.len(),
+            skipped: report.skipped.len(),
+            cancelled: report.cancelled.len(),
+            failed: report.failed.len(),
+            total,
+            elapsed_ms: batch_start.elapsed().as_millis() as u64,
+        });
+
+        log_line!(
+            "{} compression completed. {} files processed, {} failed, {} skipped, {} cancelled.",
+            settings.method.as_str(),
+            report.completed.len(),
+            report.failed.len(),
+            report.skipped.len(),
+            report.cancelled.len()
+        );
+
+        Ok(report)
+    }
+
+    /// Backend transparent inputs are sent to when the selected method can't
+    /// keep their alpha.
+    fn reroute_backend(&self) -> Result<Box<dyn Compressor>> {
+        let method = self.settings.alpha.reroute_method;
+        let backend = registry().create(method, &self.settings).ok_or_else(|| {
+            StrettaError::Settings(format!("No compressor registered for {}", method.as_str()))
+        })?;
+        if !backend.capabilities().alpha {
+            return Err(StrettaError::Settings(format!(
+                "{} can't keep transparency, so transparent images can't be rerouted to it",
+                method.as_str()
+            )));
+        }
+        Ok(backend)
+    }
+}
+
+/// A step of a running batch. `processed` counts files finished so far in any
+/// way, which together with the batch's elapsed time is enough for an ETA.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    BatchStarted {
+        total: usize,
+    },
+    FileStarted {
+        file: String,
+    },
+    FileDone {
+        file: String,
+        original_size: u64,
+        compressed_size: u64,
+        elapsed_ms: u64,
+        processed: usize,
+        total: usize,
+    },
+    FileFailed {
+        file: String,
+        stage: FailureStage,
+        error: String,
+        elapsed_ms: u64,
+        processed: usize,
+        total: usize,
+    },
+    /// No backend for the selected method accepts this input.
+    FileSkipped {
+        file: String,
+        processed: usize,
+        total: usize,
+    },
+    /// The batch was cancelled before this input started.
+    FileCancelled {
+        file: String,
+        processed: usize,
+        total: usize,
+    },
+    BatchFinished {
+        completed: usize,
+        skipped: usize,
+        cancelled: usize,
+        failed: usize,
+        total: usize,
+        elapsed_ms: u64,
+    },
+}
@@ -0,0 +1,219 @@
+use image::metadata::Orientation;
+use mozjpeg_sys::{
+    J_BOOLEAN_PARAM, JCOPY_OPTION, JCOPY_OPTION_JCOPYOPT_ALL, JCOPY_OPTION_JCOPYOPT_COMMENTS,
+    JCOPY_OPTION_JCOPYOPT_ICC, JCOPY_OPTION_JCOPYOPT_NONE, jcopy_markers_execute,
+    jcopy_markers_setup, jpeg_c_set_bool_param, jpeg_common_struct, jpeg_compress_struct,
+    jpeg_copy_critical_parameters, jpeg_create_compress, jpeg_create_decompress,
+    jpeg_decompress_struct, jpeg_destroy_compress, jpeg_destroy_decompress, jpeg_error_mgr,
+    jpeg_finish_compress, jpeg_finish_decompress, jpeg_mem_dest, jpeg_mem_src,
+    jpeg_read_coefficients, jpeg_read_header, jpeg_simple_progression, jpeg_std_error,
+    jpeg_write_coefficients, jpeg_write_marker,
+};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::mem;
+use std::os::raw::{c_int, c_uint, c_ulong};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::error::{Result, StrettaError};
+
+/// Marker for EXIF segments.
+const APP1: c_int = 0xE1;
+
+/// jpegtran-style rewriting of existing JPEGs, saved as the `lossless_jpeg`
+/// block of `AppSettings`. The DCT coefficients are copied as they are, so
+/// the pixels never change.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LosslessJpegOptions {
+    /// Rewrite as progressive, which is usually a few percent smaller.
+    pub progressive: bool,
+    pub metadata: JpegMetadata,
+}
+
+impl Default for LosslessJpegOptions {
+    fn default() -> Self {
+        Self {
+            progressive: true,
+            metadata: JpegMetadata::default(),
+        }
+    }
+}
+
+/// Which markers are carried over, like jpegtran's `-copy`. Photos that need
+/// rotating keep their orientation either way, in a minimal EXIF block when
+/// the rest of EXIF is dropped.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JpegMetadata {
+    None,
+    Comments,
+    /// Only the colour profile, which changes how the image displays.
+    #[default]
+    Icc,
+    All,
+}
+
+impl JpegMetadata {
+    fn copy_option(self) -> JCOPY_OPTION {
+        match self {
+            Self::None => JCOPY_OPTION_JCOPYOPT_NONE,
+            Self::Comments => JCOPY_OPTION_JCOPYOPT_COMMENTS,
+            Self::Icc => JCOPY_OPTION_JCOPYOPT_ICC,
+            Self::All => JCOPY_OPTION_JCOPYOPT_ALL,
+        }
+    }
+}
+
+/// Rewrites `jpeg` with Huffman tables built for the image and, optionally,
+/// progressive scans. The pixels stay as they were stored, so `orientation`,
+/// the one read from `jpeg`'s EXIF, is kept in the output.
+pub fn optimize_jpeg(
+    jpeg: &[u8],
+    orientation: Orientation,
+    options: &LosslessJpegOptions,
+) -> Result<Vec<u8>> {
+    catch_libjpeg("Failed to optimize JPEG", || {
+        transcode(jpeg, orientation, options)
+    })
+}
+
+/// Runs libjpeg calls set up with `init_error_mgr`, which report errors by
+/// unwinding out of `error_exit`, turning those errors into `StrettaError`s.
+pub(crate) fn catch_libjpeg<T>(context: &str, run: impl FnOnce() -> T) -> Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(run))
+        .map_err(|payload| StrettaError::Encode(format!("{}: {}", context, panic_message(payload))))
+}
+
+/// Makes libjpeg unwind on errors, for `catch_libjpeg`, and stay quiet about
+/// warnings.
+pub(crate) fn init_error_mgr(error: &mut jpeg_error_mgr) {
+    unsafe { jpeg_std_error(error) };
+    error.error_exit = Some(error_exit);
+    error.emit_message = Some(ignore_message);
+}
+
+fn transcode(jpeg: &[u8], orientation: Orientation, options: &LosslessJpegOptions) -> Vec<u8> {
+    let copy = options.metadata.copy_option();
+    let mut transcoder = Transcoder::new();
+    let t = &mut *transcoder;
+
+    unsafe {
+        jpeg_mem_src(&mut t.src, jpeg.as_ptr(), jpeg.len() as c_ulong);
+        jcopy_markers_setup(&mut t.src, copy);
+        jpeg_read_header(&mut t.src, 1);
+        let coefficients = jpeg_read_coefficients(&mut t.src);
+
+        jpeg_copy_critical_parameters(&t.src, &mut t.dst);
+        t.dst.optimize_coding = 1;
+        if options.progressive {
+            jpeg_simple_progression(&mut t.dst);
+        } else {
+            // mozjpeg's defaults are progressive, so drop the scan script
+            jpeg_c_set_bool_param(&mut t.dst, J_BOOLEAN_PARAM::JBOOLEAN_OPTIMIZE_SCANS, 0);
+            t.dst.scan_info = ptr::null();
+            t.dst.num_scans = 0;
+        }
+
+        jpeg_mem_dest(&mut t.dst, &mut t.output, &mut t.output_size);
+        jpeg_write_coefficients(&mut t.dst, coefficients);
+        if orientation != Orientation::NoTransforms && options.metadata != JpegMetadata::All {
+            let exif = orientation_exif(orientation);
+            jpeg_write_marker(&mut t.dst, APP1, exif.as_ptr(), exif.len() as c_uint);
+        }
+        jcopy_markers_execute(&mut t.src, &mut t.dst, copy);
+        jpeg_finish_compress(&mut t.dst);
+        jpeg_finish_decompress(&mut t.src);
+
+        std::slice::from_raw_parts(t.output, t.output_size as usize).to_vec()
+    }
+}
+
+/// APP1 segment payload holding nothing but the orientation tag, in a
+/// big-endian TIFF structure with a single IFD entry.
+fn orientation_exif(orientation: Orientation) -> Vec<u8> {
+    let mut exif = b"Exif\0\0MM\0\x2a".to_vec();
+    exif.extend_from_slice(&8u32.to_be_bytes());
+    exif.extend_from_slice(&1u16.to_be_bytes());
+    // Tag 0x0112, one SHORT, padded to four bytes
+    exif.extend_from_slice(&[0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01]);
+    exif.extend_from_slice(&[0x00, orientation.to_exif(), 0x00, 0x00]);
+    // No further IFDs
+    exif.extend_from_slice(&0u32.to_be_bytes());
+    exif
+}
+
+/// Both libjpeg objects and the buffer libjpeg writes into. Boxed because
+/// libjpeg keeps pointers to the error manager and the output fields, and
+/// torn down on drop so an unwinding error doesn't leak them.
+struct Transcoder {
+    error: jpeg_error_mgr,
+    src: jpeg_decompress_struct,
+    dst: jpeg_compress_struct,
+    output: *mut u8,
+    output_size: c_ulong,
+}
+
+impl Transcoder {
+    fn new() -> Box<Self> {
+        unsafe {
+            let mut transcoder: Box<Self> = Box::new(mem::zeroed());
+            let t = &mut *transcoder;
+            init_error_mgr(&mut t.error);
+
+            t.src.common.err = &mut t.error;
+            jpeg_create_decompress(&mut t.src);
+            t.dst.common.err = &mut t.error;
+            jpeg_create_compress(&mut t.dst);
+            transcoder
+        }
+    }
+}
+
+impl Drop for Transcoder {
+    fn drop(&mut self) {
+        unsafe {
+            jpeg_destroy_compress(&mut self.dst);
+            jpeg_destroy_decompress(&mut self.src);
+            if !self.output.is_null() {
+                libc::free(self.output.cast());
+            }
+        }
+    }
+}
+
+extern "C-unwind" fn error_exit(cinfo: &mut jpeg_common_struct) {
+    let message = unsafe { format_message(cinfo) };
+    panic::resume_unwind(Box::new(message));
+}
+
+extern "C-unwind" fn ignore_message(_cinfo: &mut jpeg_common_struct, _level: c_int) {}
+
+unsafe fn format_message(cinfo: &mut jpeg_common_struct) -> String {
+    let code = unsafe { (*cinfo.err).msg_code };
+    let Some(format) = (unsafe { (*cinfo.err).format_message }) else {
+        return format!("libjpeg error {}", code);
+    };
+
+    // The binding declares the buffer as shared, but libjpeg writes into it
+    let format = unsafe {
+        mem::transmute::<
+            unsafe extern "C-unwind" fn(&mut jpeg_common_struct, &[u8; 80]),
+            unsafe extern "C-unwind" fn(&mut jpeg_common_struct, &mut [u8; 80]),
+        >(format)
+    };
+    let mut buffer = [0u8; 80];
+    unsafe { format(cinfo, &mut buffer) };
+    let message = buffer.split(|&c| c == 0).next().unwrap_or_default();
+    String::from_utf8_lossy(message).into_owned()
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map_or_else(|| "unknown error".to_string(), |s| s.to_string()),
+    }
+}
@@ -0,0 +1,194 @@
+use crate::compressor::{Capabilities, Compressor, SourceImage, compress_file};
+use crate::error::Result;
+use crate::job::NamingPolicy;
+use crate::utility::{AppSettings, CompressionFailure, CompressionResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+#[cfg(all(feature = "jxl", feature = "gui"))]
+use {
+    crate::diagnostics::log_line,
+    crate::job::BatchReport,
+    crate::utility::{CompressionMethod, load_settings, run_app_job, start_app_batch},
+    tauri::AppHandle,
+};
+
+/// JPEG XL output through libjxl. Only registered when the app is built with
+/// the `jxl` feature, since libjxl is a C++ library that has to be installed
+/// or vendored.
+pub struct JxlCompressor {
+    pub options: JxlOptions,
+}
+
+/// Knobs for libjxl, saved as the `jxl` block of `AppSettings`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct JxlOptions {
+    /// 0 to 100. Not saved with the block; it always comes from
+    /// `AppSettings::compression_quality`.
+    #[serde(skip)]
+    pub quality: f32,
+    /// Butteraugli distance from 0 to 15, where 1 is visually lossless.
+    /// Overrides `quality` when set.
+    pub distance: Option<f32>,
+    /// Effort from 1 (fastest) to 10 (slowest, smallest).
+    pub effort: u8,
+    /// Recompress JPEG inputs without decoding them, so the original file can
+    /// be reconstructed bit for bit from the `.jxl`. Resized JPEGs are encoded
+    /// from their pixels instead.
+    pub lossless_jpeg: bool,
+}
+
+impl Default for JxlOptions {
+    fn default() -> Self {
+        Self {
+            quality: 75.0,
+            distance: None,
+            effort: 7,
+            lossless_jpeg: true,
+        }
+    }
+}
+
+impl JxlOptions {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            quality: settings.compression_quality,
+            ..settings.jxl.clone()
+        }
+    }
+}
+
+#[cfg(feature = "jxl")]
+mod encoder {
+    use super::{JxlCompressor, JxlOptions};
+    use crate::compressor::SourceImage;
+    use crate::error::{Result, StrettaError};
+    use image::ImageFormat;
+    use jpegxl_rs::encode::{EncoderFrame, EncoderSpeed};
+    use jpegxl_rs::{EncodeError, ThreadsRunner, encoder_builder};
+
+    fn encode_error(error: EncodeError) -> StrettaError {
+        StrettaError::Encode(format!("JPEG XL encoding failed: {}", error))
+    }
+
+    impl JxlOptions {
+        fn speed(&self) -> EncoderSpeed {
+            match self.effort {
+                0 | 1 => EncoderSpeed::Lightning,
+                2 => EncoderSpeed::Thunder,
+                3 => EncoderSpeed::Falcon,
+                4 => EncoderSpeed::Cheetah,
+                5 => EncoderSpeed::Hare,
+                6 => EncoderSpeed::Wombat,
+                7 => EncoderSpeed::Squirrel,
+                8 => EncoderSpeed::Kitten,
+                9 => EncoderSpeed::Tortoise,
+                _ => EncoderSpeed::Glacier,
+            }
+        }
+    }
+
+    impl JxlCompressor {
+        pub(super) fn encode_jxl(&self, source: &SourceImage) -> Result<Vec<u8>> {
+            let options = &self.options;
+            let is_jpeg = image::guess_format(&source.bytes).ok() == Some(ImageFormat::Jpeg);
+            let has_alpha = source.image.color().has_alpha();
+
+            let runner = ThreadsRunner::default();
+            let mut builder = encoder_builder();
+            builder
+                .parallel_runner(&runner)
+                .speed(options.speed())
+                .has_alpha(has_alpha);
+            match options.distance {
+                Some(distance) => builder.quality(distance.clamp(0.0, 15.0)),
+                None => builder.jpeg_quality(options.quality.clamp(0.0, 100.0)),
+            };
+
+            if is_jpeg && options.lossless_jpeg && !source.transformed {
+                // The container holds the data needed to rebuild the original JPEG
+                let mut encoder = builder
+                    .use_container(true)
+                    .uses_original_profile(true)
+                    .build()
+                    .map_err(encode_error)?;
+                let encoded = encoder.encode_jpeg(&source.bytes).map_err(encode_error)?;
+                return Ok(encoded.data);
+            }
+
+            let mut encoder = builder.build().map_err(encode_error)?;
+            let (width, height) = (source.image.width(), source.image.height());
+            let encoded = if has_alpha {
+                let pixels = source.image.to_rgba8();
+                let frame = EncoderFrame::new(pixels.as_raw()).num_channels(4);
+                encoder.encode_frame::<u8, u8>(&frame, width, height)
+            } else {
+                let pixels = source.image.to_rgb8();
+                let frame = EncoderFrame::new(pixels.as_raw()).num_channels(3);
+                encoder.encode_frame::<u8, u8>(&frame, width, height)
+            }
+            .map_err(encode_error)?;
+
+            Ok(encoded.data)
+        }
+    }
+}
+
+impl Compressor for JxlCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            lossless: false,
+            alpha: true,
+            animation: false,
+        }
+    }
+
+    fn default_extension(&self) -> &'static str {
+        "jxl"
+    }
+
+    fn accepts(&self, _input_path: &Path) -> bool {
+        true
+    }
+
+    #[cfg(feature = "jxl")]
+    fn encode(&self, source: &SourceImage) -> Result<Vec<u8>> {
+        self.encode_jxl(source)
+    }
+
+    #[cfg(not(feature = "jxl"))]
+    fn encode(&self, _source: &SourceImage) -> Result<Vec<u8>> {
+        Err(crate::error::StrettaError::Settings(
+            "This build has no JPEG XL support, rebuild with the `jxl` feature".to_string(),
+        ))
+    }
+}
+
+#[cfg(all(feature = "jxl", feature = "gui"))]
+#[tauri::command]
+pub fn jxl_compression(app: AppHandle, quality: Option<f32>) -> Result<BatchReport> {
+    log_line!("JPEG XL compression function called.");
+    start_app_batch(&app);
+
+    let saved = load_settings().unwrap_or_default();
+    let settings = AppSettings {
+        compression_quality: quality.unwrap_or(saved.compression_quality),
+        method: CompressionMethod::JpegXl,
+        ..saved
+    };
+
+    run_app_job(&app, settings)
+}
+
+pub fn compress_image_jxl(
+    input_path: &Path,
+    output_dir: &Path,
+    options: JxlOptions,
+) -> Result<CompressionResult, CompressionFailure> {
+    compress_file(
+        &JxlCompressor { options },
+        input_path,
+        output_dir,
+        &NamingPolicy::default(),
+    )
+}
@@ -0,0 +1,160 @@
+use crate::compressor::{Capabilities, Compressor, SourceImage, compress_file};
+use crate::diagnostics::log_line;
+use crate::error::{Result, StrettaError};
+use crate::gif_compressor::GifCompressor;
+use crate::job::{BatchControl, NamingPolicy};
+use crate::lossless_compressor::LosslessCompressor;
+use crate::utility::{AppSettings, CompressionFailure, CompressionResult};
+use crate::webp_compressor::{WebpCompressor, WebpOptions, is_lossless_webp};
+use image::ImageFormat;
+use std::path::Path;
+#[cfg(feature = "gui")]
+use {
+    crate::job::BatchReport,
+    crate::utility::{CompressionMethod, load_settings, run_app_job, start_app_batch},
+    tauri::AppHandle,
+};
+
+/// Writes every input back in the format it really is, through that format's
+/// optimiser: the lossless backend for JPEG, PNG and APNG, libwebp for WebP
+/// and the GIF optimiser for GIF. JPEGs keep their coefficients unless they
+/// were resized, and lossless WebP stays lossless. Other inputs are skipped.
+pub struct KeepFormatCompressor {
+    pub png: LosslessCompressor,
+    pub webp: WebpCompressor,
+    pub gif: GifCompressor,
+}
+
+impl KeepFormatCompressor {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            png: LosslessCompressor {
+                options: settings.png.clone(),
+                quantize: None,
+                jpeg: settings.lossless_jpeg.clone(),
+                webp: WebpOptions::from_settings(settings, true),
+            },
+            webp: WebpCompressor {
+                options: WebpOptions::from_settings(settings, false),
+            },
+            gif: GifCompressor {
+                options: settings.gif.clone(),
+            },
+        }
+    }
+
+    /// Picks the backend from the file's contents, whatever its extension
+    /// says.
+    fn backend(&self, source: &SourceImage) -> Result<(&dyn Compressor, ImageFormat)> {
+        let format =
+            image::guess_format(&source.bytes).map_err(|e| StrettaError::Decode(e.to_string()))?;
+        let backend: &dyn Compressor = match format {
+            // The lossless backend rewrites JPEGs without decoding them
+            ImageFormat::Jpeg | ImageFormat::Png => &self.png,
+            // The lossless backend keeps lossless WebP as WebP
+            ImageFormat::WebP if is_lossless_webp(&source.bytes) => &self.png,
+            ImageFormat::WebP => &self.webp,
+            ImageFormat::Gif => &self.gif,
+            other => {
+                return Err(StrettaError::Encode(format!(
+                    "No optimiser keeps {:?} in its own format",
+                    other
+                )));
+            }
+        };
+        Ok((backend, format))
+    }
+}
+
+impl Compressor for KeepFormatCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            lossless: false,
+            alpha: true,
+            animation: true,
+        }
+    }
+
+    /// Only a placeholder, `output_extension` follows each input's format.
+    fn default_extension(&self) -> &'static str {
+        "png"
+    }
+
+    fn output_extension(&self, source: &SourceImage) -> &'static str {
+        let Ok((_, format)) = self.backend(source) else {
+            return self.default_extension();
+        };
+        let extensions = format.extensions_str();
+        let input = source
+            .path
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+
+        // `.jpeg` stays `.jpeg`, but an extension that lies about the
+        // contents is corrected
+        extensions
+            .iter()
+            .find(|ext| ext.eq_ignore_ascii_case(input))
+            .or(extensions.first())
+            .copied()
+            .unwrap_or(self.default_extension())
+    }
+
+    fn accepts(&self, input_path: &Path) -> bool {
+        match input_path.extension().and_then(|s| s.to_str()) {
+            Some(ext) => matches!(
+                ext.to_lowercase().as_str(),
+                "jpg" | "jpeg" | "png" | "gif" | "webp"
+            ),
+            None => false,
+        }
+    }
+
+    fn encode(&self, source: &SourceImage) -> Result<Vec<u8>> {
+        self.encode_cancellable(source, &BatchControl::default())
+    }
+
+    fn encode_cancellable(&self, source: &SourceImage, control: &BatchControl) -> Result<Vec<u8>> {
+        let (backend, _) = self.backend(source)?;
+        let encoded = backend.encode_cancellable(source, control)?;
+
+        // The input is already a file in the right format, so never make it
+        // worse, unless it has been resized and no longer matches
+        if !source.transformed && encoded.len() >= source.bytes.len() {
+            log_line!(
+                "{} didn't get any smaller, keeping the original",
+                source.path.display()
+            );
+            return Ok(source.bytes.clone());
+        }
+        Ok(encoded)
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn keep_format_compression(app: AppHandle) -> Result<BatchReport> {
+    log_line!("Keep format compression function called.");
+    start_app_batch(&app);
+
+    let settings = AppSettings {
+        method: CompressionMethod::KeepFormat,
+        ..load_settings().unwrap_or_default()
+    };
+
+    run_app_job(&app, settings)
+}
+
+pub fn compress_keep_format(
+    input_path: &Path,
+    output_dir: &Path,
+    settings: &AppSettings,
+) -> Result<CompressionResult, CompressionFailure> {
+    compress_file(
+        &KeepFormatCompressor::from_settings(settings),
+        input_path,
+        output_dir,
+        &NamingPolicy::default(),
+    )
+}
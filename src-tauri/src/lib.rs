@@ -1,37 +1,60 @@
-use base64::prelude::*;
-use std::fs;
-use std::io::Read;
-use tauri::Manager;
-
-mod utility;
-mod lossy_compressor;
-mod lossless_compressor;
-mod webp_compressor;
-
+// Everything outside the modules below is the desktop app
+#[cfg(feature = "gui")]
+use {
+    base64::prelude::*,
+    std::fs,
+    std::io::Read,
+    std::sync::Arc,
+    tauri::Manager,
+    crate::error::{Result, StrettaError},
+};
+
+pub mod utility;
+pub mod alpha;
+pub mod animation;
+pub mod avif_compressor;
+pub mod compressor;
+pub mod diagnostics;
+pub mod error;
+pub mod gif_compressor;
+pub mod job;
+pub mod jpeg_optimizer;
+pub mod jxl_compressor;
+pub mod keep_format_compressor;
+pub mod lossy_compressor;
+pub mod lossless_compressor;
+pub mod png_quantizer;
+pub mod resize;
+pub mod webp_compressor;
+
+#[cfg(feature = "gui")]
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
-fn get_compressed_images() -> Result<Vec<String>, String> {
+fn get_compressed_images() -> Result<Vec<String>> {
     let mut base64_images = Vec::new();
-    let output_dir = crate::utility::get_output_path();
+    let output_dir = crate::utility::get_output_path()?;
 
-    for entry in fs::read_dir(&*output_dir).map_err(|e| e.to_string())? {
-        let entry = entry.map_err(|e| e.to_string())?;
+    for entry in fs::read_dir(output_dir).map_err(|e| StrettaError::io("Failed to read output dir", e))? {
+        let entry = entry.map_err(|e| StrettaError::io("Failed to read dir entry", e))?;
         let path = entry.path();
 
         if path.is_file() {
-            let mut file = fs::File::open(&path).map_err(|e| e.to_string())?;
+            let mut file = fs::File::open(&path).map_err(|e| StrettaError::io("Failed to open image", e))?;
             let mut buffer = Vec::new();
-            file.read_to_end(&mut buffer).map_err(|e| e.to_string())?;
+            file.read_to_end(&mut buffer).map_err(|e| StrettaError::io("Failed to read image", e))?;
 
             let mime = if let Some(ext) = path.extension() {
                 match ext.to_str().unwrap_or("").to_lowercase().as_str() {
                     "jpg" | "jpeg" => "image/jpeg",
                     "png" => "image/png",
                     "webp" => "image/webp",
+                    "avif" => "image/avif",
+                    "jxl" => "image/jxl",
                     _ => "application/octet-stream",
                 }
             } else {
@@ -46,42 +69,48 @@ fn get_compressed_images() -> Result<Vec<String>, String> {
     Ok(base64_images)
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
-fn export_compressed_images(destination: String) -> Result<(), String> {
-    let output_dir = crate::utility::get_output_path();
+fn export_compressed_images(destination: String) -> Result<()> {
+    let output_dir = crate::utility::get_output_path()?;
 
-    for entry in std::fs::read_dir(&*output_dir).map_err(|e| e.to_string())? {
-        let entry = entry.map_err(|e| e.to_string())?;
+    for entry in std::fs::read_dir(output_dir).map_err(|e| StrettaError::io("Failed to read output dir", e))? {
+        let entry = entry.map_err(|e| StrettaError::io("Failed to read dir entry", e))?;
         let path = entry.path();
         if path.is_file() {
-            let file_name = path.file_name().ok_or("Invalid file name")?;
+            let file_name = path
+                .file_name()
+                .ok_or_else(|| StrettaError::Validation("Invalid file name".to_string()))?;
             let dest_path = std::path::Path::new(&destination).join(file_name);
-            fs::copy(&path, &dest_path).map_err(|e| e.to_string())?;
+            fs::copy(&path, &dest_path).map_err(|e| StrettaError::io("Failed to export image", e))?;
         }
     }
 
     Ok(())
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
-fn get_original_images() -> Result<Vec<String>, String> {
+fn get_original_images() -> Result<Vec<String>> {
     let mut base64_images = Vec::new();
-    let input_dir = crate::utility::get_input_path();
+    let input_dir = crate::utility::get_input_path()?;
 
-    for entry in fs::read_dir(&*input_dir).map_err(|e| e.to_string())? {
-        let entry = entry.map_err(|e| e.to_string())?;
+    for entry in fs::read_dir(input_dir).map_err(|e| StrettaError::io("Failed to read input dir", e))? {
+        let entry = entry.map_err(|e| StrettaError::io("Failed to read dir entry", e))?;
         let path = entry.path();
 
         if path.is_file() {
-            let mut file = fs::File::open(&path).map_err(|e| e.to_string())?;
+            let mut file = fs::File::open(&path).map_err(|e| StrettaError::io("Failed to open image", e))?;
             let mut buffer = Vec::new();
-            file.read_to_end(&mut buffer).map_err(|e| e.to_string())?;
+            file.read_to_end(&mut buffer).map_err(|e| StrettaError::io("Failed to read image", e))?;
 
             let mime = if let Some(ext) = path.extension() {
                 match ext.to_str().unwrap_or("").to_lowercase().as_str() {
                     "jpg" | "jpeg" => "image/jpeg",
                     "png" => "image/png",
                     "webp" => "image/webp",
+                    "avif" => "image/avif",
+                    "jxl" => "image/jxl",
                     _ => "application/octet-stream",
                 }
             } else {
@@ -96,12 +125,14 @@ fn get_original_images() -> Result<Vec<String>, String> {
     Ok(base64_images)
 }
 
+#[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
+        .manage(Arc::new(job::BatchControl::default()))
         .setup(|app| {
-            let app_data = app.path().app_data_dir().unwrap();
+            let app_data = app.path().app_data_dir()?;
             crate::utility::initialize_image_paths(app_data.clone())?;
             crate::utility::initialize_settings_path(app_data.clone())?;
 
@@ -114,13 +145,23 @@ pub fn run() {
             get_compressed_images,
             export_compressed_images,
             get_original_images,
+            diagnostics::get_diagnostics,
             utility::save_settings,
             utility::load_settings,
             utility::handle_compression,
             utility::handle_images,
+            utility::cancel_compression,
+            utility::pause_compression,
+            utility::resume_compression,
             webp_compressor::webp_compression,
             lossy_compressor::lossy_compression,
-            lossless_compressor::lossless_compression
+            lossless_compressor::lossless_compression,
+            lossless_compressor::quantized_png_compression,
+            gif_compressor::gif_compression,
+            avif_compressor::avif_compression,
+            #[cfg(feature = "jxl")]
+            jxl_compressor::jxl_compression,
+            keep_format_compressor::keep_format_compression
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
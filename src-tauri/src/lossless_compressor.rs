@@ -1,108 +1,447 @@
-use crate::lossy_compressor::compress_image_lossy;
-use crate::utility::{
-    clear_output_folder, deduplicate_path, encode_file, get_input_path, get_output_path,
-    CompressionResult,
+use crate::animation::Animation;
+use crate::compressor::{Capabilities, Compressor, SourceImage, compress_file};
+use crate::diagnostics::log_line;
+use crate::error::{Result, StrettaError};
+use crate::job::{BatchControl, NamingPolicy};
+use crate::jpeg_optimizer::{LosslessJpegOptions, optimize_jpeg};
+use crate::lossy_compressor::{ChromaSubsampling, JpegOptions, LossyCompressor};
+use crate::png_quantizer::{Palettized, QuantizeOptions, quantize};
+use crate::utility::{CompressionFailure, CompressionResult};
+use crate::webp_compressor::{WebpOptions, optimize_webp};
+use image::ImageFormat;
+use image::metadata::Orientation;
+use oxipng::{
+    BitDepth, ColorType, Deflaters, IndexSet, Interlacing, Options, PngError, PngResult, RGBA8,
+    RawImage, StripChunks, optimize_from_memory,
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::num::NonZeroU8;
+use std::path::Path;
+use std::time::{Duration, Instant};
+#[cfg(feature = "gui")]
+use {
+    crate::job::BatchReport,
+    crate::utility::{AppSettings, CompressionMethod, load_settings, run_app_job, start_app_batch},
+    tauri::AppHandle,
 };
-use oxipng::{optimize, InFile, Options, OutFile};
-use rayon::prelude::*;
-use std::fs;
-use std::path::PathBuf;
 
-#[tauri::command]
-pub fn lossless_compression() -> Result<Vec<CompressionResult>, String> {
-    println!("Lossless compression function called.");
-
-    let input_dir = get_input_path().clone();
-    let output_dir = get_output_path().clone();
-    println!("Input path: {:?}", &input_dir);
-    println!("Output path: {:?}", &output_dir);
-
-    // Clear the output folder
-    clear_output_folder().map_err(|e| format!("Failed to clear output folder: {}", e))?;
-    fs::create_dir_all(&output_dir).map_err(|e| format!("Failed to create output dir: {}", e))?;
-
-    // Read all files in the input directory
-    let input_files: Vec<PathBuf> = fs::read_dir(&input_dir)
-        .map_err(|e| format!("Failed to read input dir: {}", e))?
-        .filter_map(|res| res.ok())
-        .map(|entry| entry.path())
-        .filter(|p| p.is_file())
-        .collect();
+/// PNG output through oxipng. JPEG inputs stay JPEG and are rewritten
+/// without touching their pixels, or re-encoded at the highest quality once
+/// resized. WebP inputs stay lossless WebP unless quantising. Other animated
+/// inputs are written as APNG.
+pub struct LosslessCompressor {
+    pub options: PngOptions,
+    /// Reduce to a palette before optimising, which makes the output lossy.
+    pub quantize: Option<QuantizeOptions>,
+    pub jpeg: LosslessJpegOptions,
+    pub webp: WebpOptions,
+}
 
-    // Process each file
-    let results: Vec<CompressionResult> = input_files
-        .par_iter()
-        .filter_map(|input| {
-            if is_lossless_compatible(&input) {
-                compress_image_lossless(&input, &output_dir).ok()
-            } else {
-                compress_image_lossy(&input, &output_dir).ok()
+/// Knobs for oxipng, saved as the `png` block of `AppSettings`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PngOptions {
+    /// oxipng preset from 0 (fast) to 6 (slowest, smallest).
+    pub level: u8,
+    /// Deflate with Zopfli instead of libdeflate. Much slower, a few percent
+    /// smaller.
+    pub zopfli: bool,
+    /// Zopfli passes per file. Big images want fewer.
+    pub zopfli_iterations: u8,
+    pub interlace: PngInterlace,
+    pub strip: ChunkStripping,
+    pub bit_depth_reduction: bool,
+    pub palette_reduction: bool,
+    /// Stop trying further optimisations for a file after this many seconds
+    /// and keep the best result so far.
+    pub timeout_secs: Option<u64>,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        Self {
+            level: 6,
+            zopfli: false,
+            zopfli_iterations: 15,
+            interlace: PngInterlace::Off,
+            strip: ChunkStripping::Safe,
+            bit_depth_reduction: true,
+            palette_reduction: true,
+            timeout_secs: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PngInterlace {
+    #[default]
+    Off,
+    /// Adam7, which renders progressively. oxipng leaves it off when it would
+    /// make the file larger.
+    On,
+    /// Whatever the input used.
+    Keep,
+}
+
+/// Which ancillary chunks to drop. Chunk names are the four-letter PNG names,
+/// e.g. `iCCP` or `tEXt`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "mode", content = "chunks", rename_all = "snake_case")]
+pub enum ChunkStripping {
+    None,
+    /// Everything that doesn't affect how the image displays.
+    #[default]
+    Safe,
+    All,
+    /// Everything except these.
+    Keep(Vec<String>),
+    /// Only these.
+    Strip(Vec<String>),
+}
+
+impl PngOptions {
+    fn oxipng_options(&self) -> Result<Options> {
+        let mut options = Options::from_preset(self.level.min(6));
+
+        if self.zopfli {
+            let iterations = NonZeroU8::new(self.zopfli_iterations).ok_or_else(|| {
+                StrettaError::Settings("Zopfli needs at least one iteration".to_string())
+            })?;
+            options.deflate = Deflaters::Zopfli { iterations };
+        }
+
+        options.interlace = match self.interlace {
+            PngInterlace::Off => Some(Interlacing::None),
+            PngInterlace::On => Some(Interlacing::Adam7),
+            PngInterlace::Keep => None,
+        };
+
+        // Stripping the APNG chunks would leave only the first frame, so they
+        // are always kept
+        options.strip = match &self.strip {
+            ChunkStripping::None => StripChunks::None,
+            ChunkStripping::Safe => StripChunks::Safe,
+            ChunkStripping::All => StripChunks::Keep(ANIMATION_CHUNKS.into_iter().collect()),
+            ChunkStripping::Keep(names) => {
+                let mut names = chunk_names(names)?;
+                names.extend(ANIMATION_CHUNKS);
+                StripChunks::Keep(names)
+            }
+            ChunkStripping::Strip(names) => {
+                let mut names = chunk_names(names)?;
+                names.retain(|name| !ANIMATION_CHUNKS.contains(name));
+                StripChunks::Strip(names)
             }
+        };
+
+        options.bit_depth_reduction = self.bit_depth_reduction;
+        options.palette_reduction = self.palette_reduction;
+        options.timeout = self.timeout_secs.map(Duration::from_secs);
+
+        Ok(options)
+    }
+
+    /// Runs oxipng, stopping early where it can once `control` is cancelled.
+    /// oxipng can't be interrupted from outside, and with Zopfli on nearly
+    /// all of its time goes on the final deflate, so `first_pass` does the
+    /// reductions and filter trials with the preset's libdeflate and Zopfli
+    /// only recompresses that result if the batch is still running.
+    fn optimize(
+        &self,
+        control: &BatchControl,
+        first_pass: impl FnOnce(&Options) -> PngResult<Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        let optimize_error =
+            |e: PngError| StrettaError::Encode(format!("Failed to optimize PNG: {}", e));
+        let started = Instant::now();
+        let mut options = self.oxipng_options()?;
+        if control.is_cancelled() {
+            // Skips the trials, leaving only what's needed to write the file
+            options.timeout = Some(Duration::ZERO);
+        }
+        if !matches!(options.deflate, Deflaters::Zopfli { .. }) {
+            return first_pass(&options).map_err(optimize_error);
+        }
+
+        let fast = Options {
+            deflate: Options::from_preset(self.level.min(6)).deflate,
+            ..options.clone()
+        };
+        let png = first_pass(&fast).map_err(optimize_error)?;
+
+        let remaining = options
+            .timeout
+            .map(|timeout| timeout.saturating_sub(started.elapsed()));
+        if control.is_cancelled() || remaining == Some(Duration::ZERO) {
+            return Ok(png);
+        }
+        options.timeout = remaining;
+        optimize_from_memory(&png, &options).map_err(optimize_error)
+    }
+}
+
+/// Animation control, frame control and frame data.
+const ANIMATION_CHUNKS: [[u8; 4]; 3] = [*b"acTL", *b"fcTL", *b"fdAT"];
+
+fn chunk_names(names: &[String]) -> Result<IndexSet<[u8; 4]>> {
+    names
+        .iter()
+        .map(|name| {
+            name.as_bytes()
+                .try_into()
+                .ok()
+                .filter(|bytes: &[u8; 4]| bytes.iter().all(u8::is_ascii_alphabetic))
+                .ok_or_else(|| StrettaError::Settings(format!("Invalid PNG chunk name: {}", name)))
         })
+        .collect()
+}
+
+impl Compressor for LosslessCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            lossless: self.quantize.is_none(),
+            alpha: true,
+            animation: true,
+        }
+    }
+
+    fn default_extension(&self) -> &'static str {
+        "png"
+    }
+
+    fn output_extension(&self, source: &SourceImage) -> &'static str {
+        match self.kept_format(source) {
+            Some(ImageFormat::Jpeg) => "jpg",
+            Some(ImageFormat::WebP) => "webp",
+            _ => self.default_extension(),
+        }
+    }
+
+    fn accepts(&self, input_path: &Path) -> bool {
+        is_lossless_compatible(input_path)
+    }
+
+    fn encode(&self, source: &SourceImage) -> Result<Vec<u8>> {
+        self.encode_cancellable(source, &BatchControl::default())
+    }
+
+    fn encode_cancellable(&self, source: &SourceImage, control: &BatchControl) -> Result<Vec<u8>> {
+        match self.kept_format(source) {
+            Some(ImageFormat::Jpeg) if source.transformed => {
+                return encode_max_quality_jpeg(source);
+            }
+            Some(ImageFormat::Jpeg) => {
+                return optimize_jpeg(&source.bytes, source.orientation, &self.jpeg);
+            }
+            Some(ImageFormat::WebP) => return optimize_webp(source, &self.webp),
+            _ => {}
+        }
+
+        if let Some(quantize_options) = &self.quantize {
+            if source.animation.is_some() {
+                // APNG frames share one palette, so animations keep full colour
+                log_line!("{} is animated, keeping full colour", source.path.display());
+            } else {
+                match quantize(&source.image, quantize_options) {
+                    Some(palettized) => {
+                        return encode_palettized(source, palettized, &self.options, control);
+                    }
+                    None => log_line!(
+                        "{} can't reach quality {} with 256 colours, keeping full colour",
+                        source.path.display(),
+                        quantize_options.min_quality
+                    ),
+                }
+            }
+        }
+
+        let is_png = image::guess_format(&source.bytes).ok() == Some(ImageFormat::Png);
+        // A PNG turned upright is written from its pixels, since oxipng's safe
+        // stripping drops the eXIf chunk that held the orientation
+        let upright = source.orientation == Orientation::NoTransforms;
+        let png = if is_png && upright && !source.transformed {
+            // APNG inputs go straight through, oxipng recompresses each frame
+            Cow::Borrowed(&source.bytes)
+        } else if let Some(animation) = &source.animation {
+            Cow::Owned(encode_apng(animation)?)
+        } else {
+            // oxipng only reads PNG, so convert other formats first
+            let mut png = Vec::new();
+            source
+                .image
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                .map_err(|e| StrettaError::Encode(format!("Failed to convert to PNG: {}", e)))?;
+            Cow::Owned(png)
+        };
+
+        self.options
+            .optimize(control, |options| optimize_from_memory(&png, options))
+    }
+}
+
+/// Resized JPEGs have no coefficients left to keep, and as PNG they'd only
+/// grow, so they're re-encoded with as little loss as mozjpeg allows.
+fn encode_max_quality_jpeg(source: &SourceImage) -> Result<Vec<u8>> {
+    let encoder = LossyCompressor {
+        options: JpegOptions {
+            quality: 100.0,
+            chroma_subsampling: ChromaSubsampling::Yuv444,
+            ..JpegOptions::default()
+        },
+    };
+    encoder.encode(source)
+}
+
+/// Writes every frame in full, leaving oxipng to squeeze the frame data.
+fn encode_apng(animation: &Animation) -> Result<Vec<u8>> {
+    let encode_error = |e: png::EncodingError| {
+        StrettaError::Encode(format!("Failed to write animated PNG: {}", e))
+    };
+
+    let (width, height) = animation.canvas_size();
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(animation.frames.len() as u32, animation.loop_count)
+        .map_err(encode_error)?;
+
+    let mut writer = encoder.write_header().map_err(encode_error)?;
+    for frame in &animation.frames {
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let delay_ms = (numer / denom.max(1)).min(u32::from(u16::MAX)) as u16;
+        writer
+            .set_frame_delay(delay_ms, 1000)
+            .map_err(encode_error)?;
+        writer
+            .write_image_data(frame.buffer().as_raw())
+            .map_err(encode_error)?;
+    }
+    writer.finish().map_err(encode_error)?;
+
+    Ok(png)
+}
+
+fn encode_palettized(
+    source: &SourceImage,
+    palettized: Palettized,
+    options: &PngOptions,
+    control: &BatchControl,
+) -> Result<Vec<u8>> {
+    log_line!(
+        "Quantised {} to {} colours at quality {}",
+        source.path.display(),
+        palettized.palette.len(),
+        palettized.quality
+    );
+
+    let palette = palettized
+        .palette
+        .iter()
+        .map(|&[r, g, b, a]| RGBA8::new(r, g, b, a))
         .collect();
+    let raw = RawImage::new(
+        source.image.width(),
+        source.image.height(),
+        ColorType::Indexed { palette },
+        BitDepth::Eight,
+        palettized.indices,
+    )
+    .map_err(|e| StrettaError::Encode(format!("Failed to build palette PNG: {}", e)))?;
+
+    options.optimize(control, |options| raw.create_optimized_png(options))
+}
 
-    println!("Compression completed. {} files processed.", results.len());
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn lossless_compression(app: AppHandle) -> Result<BatchReport> {
+    log_line!("Lossless compression function called.");
+    start_app_batch(&app);
+
+    let settings = AppSettings {
+        method: CompressionMethod::Lossless,
+        ..load_settings().unwrap_or_default()
+    };
 
-    Ok(results)
+    run_app_job(&app, settings)
 }
 
-fn is_lossless_compatible(path: &PathBuf) -> bool {
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn quantized_png_compression(app: AppHandle) -> Result<BatchReport> {
+    log_line!("Quantised PNG compression function called.");
+    start_app_batch(&app);
+
+    let settings = AppSettings {
+        method: CompressionMethod::QuantizedPng,
+        ..load_settings().unwrap_or_default()
+    };
+
+    run_app_job(&app, settings)
+}
+
+fn is_lossless_compatible(path: &Path) -> bool {
     match path.extension().and_then(|s| s.to_str()) {
-        Some(ext) => matches!(ext.to_lowercase().as_str(), "png" | "webp"),
+        Some(ext) => matches!(
+            ext.to_lowercase().as_str(),
+            "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp"
+        ),
         None => false,
     }
 }
 
-fn compress_image_lossless(
-    input_path: &PathBuf,
-    output_dir: &PathBuf,
-) -> Result<CompressionResult, String> {
-    let file_stem = input_path.file_stem().unwrap().to_string_lossy();
-    //let ext = input_path.extension().unwrap_or_default().to_string_lossy();
-    let ext = "png"; // Assuming PNG for compression, adjust as needed
-    let initial_path = output_dir.join(format!("{}_compressed.{}", file_stem, ext));
-    let output_path = deduplicate_path(&initial_path);
-
-    // Copy the file first, then optimize in place
-    fs::copy(&input_path, &output_path).map_err(|e| format!("Failed to copy PNG: {}", e))?;
-
-    let mut options = Options::max_compression();
-    options.strip = oxipng::StripChunks::Safe; // Strip metadata?
-
-    // Optimize in place using the copied file
-    let input_file = InFile::Path(output_path.clone());
-    let output_file = OutFile::from_path(output_path.clone());
-
-    optimize(&input_file, &output_file, &options)
-        .map_err(|e| format!("Failed to optimize PNG: {}", e))?;
-
-    let original_size = fs::metadata(&input_path).map(|m| m.len()).unwrap_or(0);
-    let compressed_size = fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
-    let reduction_percent = if original_size > 0 && compressed_size <= original_size {
-        100.0 * (original_size - compressed_size) as f32 / original_size as f32
-    } else if original_size > 0 && compressed_size > original_size {
-        // Compression resulted in larger file, show negative reduction
-        -100.0 * (compressed_size - original_size) as f32 / original_size as f32
-    } else {
-        0.0
-    };
+impl LosslessCompressor {
+    /// Input formats that are optimised in place rather than turned into PNG.
+    fn kept_format(&self, source: &SourceImage) -> Option<ImageFormat> {
+        match image::guess_format(&source.bytes).ok()? {
+            ImageFormat::Jpeg => Some(ImageFormat::Jpeg),
+            ImageFormat::WebP if self.quantize.is_none() => Some(ImageFormat::WebP),
+            _ => None,
+        }
+    }
+}
 
-    println!(
-        "Compressed {} to {}: {} bytes -> {} bytes, {:.2}% reduction",
-        input_path.display(),
-        output_path.display(),
-        original_size,
-        compressed_size,
-        reduction_percent
-    );
+pub fn compress_image_lossless(
+    input_path: &Path,
+    output_dir: &Path,
+    options: PngOptions,
+    jpeg: LosslessJpegOptions,
+    webp: WebpOptions,
+) -> Result<CompressionResult, CompressionFailure> {
+    compress_file(
+        &LosslessCompressor {
+            options,
+            quantize: None,
+            jpeg,
+            webp,
+        },
+        input_path,
+        output_dir,
+        &NamingPolicy::default(),
+    )
+}
 
-    Ok(CompressionResult {
-        original_path: input_path.display().to_string(),
-        compressed_path: output_path.display().to_string(),
-        original_size,
-        compressed_size,
-        reduction_percent,
-        original_base64: encode_file(&input_path.to_string_lossy())?,
-        compressed_base64: encode_file(&output_path.to_string_lossy())?,
-    })
+pub fn compress_image_quantized(
+    input_path: &Path,
+    output_dir: &Path,
+    options: PngOptions,
+    quantize: QuantizeOptions,
+    jpeg: LosslessJpegOptions,
+) -> Result<CompressionResult, CompressionFailure> {
+    compress_file(
+        &LosslessCompressor {
+            options,
+            quantize: Some(quantize),
+            jpeg,
+            // WebP inputs become palette PNGs when quantising
+            webp: WebpOptions::default(),
+        },
+        input_path,
+        output_dir,
+        &NamingPolicy::default(),
+    )
 }
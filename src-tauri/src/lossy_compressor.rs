@@ -1,118 +1,289 @@
-
-use image::GenericImageView;
+use image::RgbImage;
+use mozjpeg::qtable::{self, QTable};
 use mozjpeg::{ColorSpace, Compress};
-use rayon::prelude::*;
-use std::{
-    fs,
-    path::{PathBuf},
+use mozjpeg_sys::{
+    J_BOOLEAN_PARAM, jpeg_c_set_bool_param, jpeg_common_struct, jpeg_compress_struct,
+    jpeg_error_mgr,
 };
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::mem;
+use std::path::Path;
 
-use crate::utility::{
-    clear_output_folder, deduplicate_path, get_input_path, get_output_path, CompressionResult, encode_file
+use crate::compressor::{Capabilities, Compressor, SourceImage, compress_file};
+use crate::error::{Result, StrettaError};
+use crate::job::NamingPolicy;
+use crate::jpeg_optimizer::{catch_libjpeg, init_error_mgr};
+use crate::utility::{AppSettings, CompressionFailure, CompressionResult};
+#[cfg(feature = "gui")]
+use {
+    crate::diagnostics::log_line,
+    crate::job::BatchReport,
+    crate::utility::{CompressionMethod, load_settings, run_app_job, start_app_batch},
+    tauri::AppHandle,
 };
 
-#[tauri::command]
-pub fn lossy_compression() -> Result<Vec<CompressionResult>, String> {
-    // This function implements lossy compression using mozjpeg
-    println!("Lossy compression function called.");
+/// Knobs for the mozjpeg encoder, saved as the `jpeg` block of `AppSettings`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct JpegOptions {
+    /// 0 to 100 on mozjpeg's scale. Not saved with the block; it always comes
+    /// from `AppSettings::compression_quality`.
+    #[serde(skip)]
+    pub quality: f32,
+    pub progressive: bool,
+    pub chroma_subsampling: ChromaSubsampling,
+    /// Trellis quantisation of the AC and DC coefficients. Turning it off
+    /// encodes faster and a little larger, and leaves the rest of mozjpeg's
+    /// compression profile as it is.
+    pub trellis: bool,
+    /// Let mozjpeg search for the smallest progressive scan script. Ignored
+    /// for baseline output.
+    pub optimize_scans: bool,
+    /// 0 disables smoothing, 1 to 100 blurs away noise before encoding.
+    pub smoothing: u8,
+    pub quant_table: QuantTable,
+}
 
-    let input_dir = get_input_path().clone();
-    let output_dir = get_output_path().clone();
+impl Default for JpegOptions {
+    fn default() -> Self {
+        Self {
+            quality: 75.0,
+            progressive: true,
+            chroma_subsampling: ChromaSubsampling::default(),
+            trellis: true,
+            optimize_scans: true,
+            smoothing: 0,
+            quant_table: QuantTable::default(),
+        }
+    }
+}
 
-    println!("Input path: {:?}", &input_dir);
-    println!("Output path: {:?}", &output_dir);
+impl JpegOptions {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            quality: settings.compression_quality,
+            ..settings.jpeg.clone()
+        }
+    }
+}
 
-    clear_output_folder().expect("Failed to clear output folder: {}");
-    fs::create_dir_all(&output_dir).map_err(|e| format!("Failed to create output dir: {}", e))?;
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    #[serde(rename = "4:4:4")]
+    Yuv444,
+    #[serde(rename = "4:2:2")]
+    Yuv422,
+    #[default]
+    #[serde(rename = "4:2:0")]
+    Yuv420,
+}
 
-    let input_files: Vec<PathBuf> = fs::read_dir(&input_dir)
-        .map_err(|e| format!("Failed to read input dir: {}", e))?
-        .filter_map(|res| res.ok())
-        .map(|entry| entry.path())
-        .filter(|p| p.is_file() && is_jpeg_compatible(p))
-        .collect();
+impl ChromaSubsampling {
+    /// Size of a chroma sample in luma pixels, for both Cb and Cr.
+    fn pixel_size(self) -> (u8, u8) {
+        match self {
+            Self::Yuv444 => (1, 1),
+            Self::Yuv422 => (2, 1),
+            Self::Yuv420 => (2, 2),
+        }
+    }
+}
 
-    let results: Vec<CompressionResult> = input_files
-        .par_iter()
-        .filter_map(|input| compress_image_lossy(input, &output_dir).ok())
-        .collect();
+/// Quantisation table presets. Everything but `Mozjpeg` is scaled to the
+/// quality setting the same way mozjpeg scales its own tables.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantTable {
+    /// Whatever mozjpeg picks for the quality, currently ImageMagick's tables.
+    #[default]
+    Mozjpeg,
+    AnnexK,
+    Flat,
+    MsSsim,
+    Robidoux,
+    PsnrHvs,
+    KleinSilversteinCarney,
+    WatsonTaylorBorthwick,
+    AhumadaWatsonPeterson,
+    PetersonAhumadaWatson,
+}
 
-    println!("Compression completed. {} files processed.", results.len());
+impl QuantTable {
+    /// Luma and chroma tables, or `None` to keep mozjpeg's own.
+    fn tables(self) -> Option<(&'static QTable, &'static QTable)> {
+        let tables = match self {
+            Self::Mozjpeg => return None,
+            Self::AnnexK => (&qtable::AnnexK_Luma, &qtable::AnnexK_Chroma),
+            Self::Flat => (&qtable::Flat, &qtable::Flat),
+            Self::MsSsim => (&qtable::MSSSIM_Luma, &qtable::MSSSIM_Chroma),
+            Self::Robidoux => (&qtable::NRobidoux, &qtable::NRobidoux),
+            Self::PsnrHvs => (&qtable::PSNRHVS_Luma, &qtable::PSNRHVS_Chroma),
+            Self::KleinSilversteinCarney => (
+                &qtable::KleinSilversteinCarney,
+                &qtable::KleinSilversteinCarney,
+            ),
+            Self::WatsonTaylorBorthwick => (
+                &qtable::WatsonTaylorBorthwick,
+                &qtable::WatsonTaylorBorthwick,
+            ),
+            Self::AhumadaWatsonPeterson => (
+                &qtable::AhumadaWatsonPeterson,
+                &qtable::AhumadaWatsonPeterson,
+            ),
+            Self::PetersonAhumadaWatson => (
+                &qtable::PetersonAhumadaWatson,
+                &qtable::PetersonAhumadaWatson,
+            ),
+        };
+        Some(tables)
+    }
+}
+
+/// JPEG output through mozjpeg.
+pub struct LossyCompressor {
+    pub options: JpegOptions,
+}
+
+impl Compressor for LossyCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            lossless: false,
+            alpha: false,
+            animation: false,
+        }
+    }
+
+    fn default_extension(&self) -> &'static str {
+        "jpg"
+    }
+
+    fn accepts(&self, input_path: &Path) -> bool {
+        is_jpeg_compatible(input_path)
+    }
 
-    Ok(results)
+    fn encode(&self, source: &SourceImage) -> Result<Vec<u8>> {
+        let image = source.image.to_rgb8();
+        if image.width() == 0 || image.height() == 0 {
+            return Err(StrettaError::Encode(
+                "Cannot encode an empty image as JPEG".to_string(),
+            ));
+        }
+
+        // mozjpeg reports libjpeg errors by unwinding, so they're caught here
+        catch_libjpeg("Failed to encode JPEG", || self.compress(&image))?
+            .map_err(|e| StrettaError::Encode(format!("Failed to encode JPEG: {}", e)))
+    }
 }
 
-fn is_jpeg_compatible(path: &PathBuf) -> bool {
+impl LossyCompressor {
+    fn compress(&self, image: &RgbImage) -> io::Result<Vec<u8>> {
+        let mut comp = if self.options.trellis {
+            Compress::new(ColorSpace::JCS_RGB)
+        } else {
+            Compress::new_err(trellis_off_error_mgr(), ColorSpace::JCS_RGB)
+        };
+        self.configure(&mut comp);
+        comp.set_size(image.width() as usize, image.height() as usize);
+
+        let mut output = Vec::new();
+        let mut started = comp.start_compress(&mut output)?;
+        started.write_scanlines(image.as_raw())?;
+        started.finish()?;
+        Ok(output)
+    }
+
+    /// Applies the options on top of mozjpeg's maximum compression profile.
+    fn configure(&self, comp: &mut Compress) {
+        let options = &self.options;
+
+        let quality = options.quality.clamp(1.0, 100.0);
+        match options.quant_table.tables() {
+            Some((luma, chroma)) => {
+                comp.set_luma_qtable(&luma.scaled(quality, quality));
+                comp.set_chroma_qtable(&chroma.scaled(quality, quality));
+            }
+            None => comp.set_quality(quality),
+        }
+
+        let chroma = options.chroma_subsampling.pixel_size();
+        comp.set_chroma_sampling_pixel_sizes(chroma, chroma);
+        comp.set_smoothing_factor(options.smoothing.min(100));
+
+        if options.progressive {
+            comp.set_optimize_scans(options.optimize_scans);
+            comp.set_progressive_mode();
+        } else {
+            // Also drops the progressive scan script mozjpeg sets up by default
+            comp.set_optimize_scans(false);
+        }
+    }
+}
+
+/// `Compress` has no setter for trellis quantisation and keeps its
+/// `jpeg_compress_struct` private, and `set_fastest_defaults` would swap out
+/// the whole profile. libjpeg calls the error manager's `reset_error_mgr` from
+/// `jpeg_start_compress`, after every setter and before the parameters are
+/// read, so that hook switches off just the two trellis flags.
+fn trellis_off_error_mgr() -> Box<jpeg_error_mgr> {
+    let mut error: Box<jpeg_error_mgr> = Box::new(unsafe { mem::zeroed() });
+    init_error_mgr(&mut error);
+    error.reset_error_mgr = Some(disable_trellis);
+    error
+}
+
+extern "C-unwind" fn disable_trellis(common: &mut jpeg_common_struct) {
+    // What libjpeg's own reset_error_mgr does
+    unsafe {
+        (*common.err).num_warnings = 0;
+        (*common.err).msg_code = 0;
+    }
+
+    // Only ever installed on a compressor, whose struct starts with the
+    // common fields
+    let cinfo = unsafe { &mut *(common as *mut jpeg_common_struct).cast::<jpeg_compress_struct>() };
+    unsafe {
+        jpeg_c_set_bool_param(cinfo, J_BOOLEAN_PARAM::JBOOLEAN_TRELLIS_QUANT, 0);
+        jpeg_c_set_bool_param(cinfo, J_BOOLEAN_PARAM::JBOOLEAN_TRELLIS_QUANT_DC, 0);
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn lossy_compression(app: AppHandle, quality: Option<f32>) -> Result<BatchReport> {
+    // This function implements lossy compression using mozjpeg
+    log_line!("Lossy compression function called.");
+    start_app_batch(&app);
+
+    let saved = load_settings().unwrap_or_default();
+    let settings = AppSettings {
+        compression_quality: quality.unwrap_or(saved.compression_quality),
+        method: CompressionMethod::Lossy,
+        ..saved
+    };
+
+    run_app_job(&app, settings)
+}
+
+fn is_jpeg_compatible(path: &Path) -> bool {
     match path.extension().and_then(|s| s.to_str()) {
-        Some(ext) => matches!(ext.to_lowercase().as_str(), "jpg" | "jpeg" | "png"),
+        Some(ext) => matches!(
+            ext.to_lowercase().as_str(),
+            "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp"
+        ),
         None => false,
     }
 }
 
 pub fn compress_image_lossy(
-    input_path: &PathBuf,
-    output_dir: &PathBuf,
-) -> Result<CompressionResult, String> {
-    let img = image::open(&input_path).map_err(|e| format!("Image open failed: {}", e))?;
-    let image_data = img.to_rgb8();
-
-    let mut comp = Compress::new(ColorSpace::JCS_RGB);
-    comp.set_quality(75.0);
-    comp.set_progressive_mode();
-
-    let (w, h) = img.dimensions();
-    comp.set_size(w as usize, h as usize);
-
-    let mut compressed_bytes = Vec::new();
-    let mut comp_writer = comp
-        .start_compress(&mut compressed_bytes)
-        .map_err(|e| format!("Start compress failed: {}", e))?;
-
-    comp_writer
-        .write_scanlines(image_data.as_flat_samples().as_slice())
-        .map_err(|e| format!("Write scanlines failed: {}", e))?;
-
-    comp_writer
-        .finish()
-        .map_err(|e| format!("Finish compress failed: {}", e))?;
-
-    let file_stem = input_path.file_stem().unwrap().to_string_lossy();
-    //let ext = input_path.extension().unwrap_or_default().to_string_lossy();
-    let ext = "jpg"; // Assuming JPEG for compression, adjust as needed
-    let initial_path = output_dir.join(format!("{}_compressed.{}", file_stem, ext));
-
-    let output_path = deduplicate_path(&initial_path);
-    fs::write(&output_path, &compressed_bytes)
-        .map_err(|e| format!("Write output failed: {}", e))?;
-
-    let original_size = fs::metadata(&input_path).map(|m| m.len()).unwrap_or(0);
-    let compressed_size = compressed_bytes.len() as u64;
-
-    let reduction_percent = if original_size > 0 && compressed_size <= original_size {
-        100.0 * (original_size - compressed_size) as f32 / original_size as f32
-    } else if original_size > 0 && compressed_size > original_size {
-        // Compression resulted in larger file, show negative reduction
-        -100.0 * (compressed_size - original_size) as f32 / original_size as f32
-    } else {
-        0.0
-    };
-
-    println!(
-        "Compressed {} to {}: {} bytes -> {} bytes, {:.2}% reduction",
-        input_path.display(),
-        output_path.display(),
-        original_size,
-        compressed_size,
-        reduction_percent
-    );
-
-    Ok(CompressionResult {
-        original_path: input_path.display().to_string(),
-        compressed_path: output_path.display().to_string(),
-        original_size,
-        compressed_size,
-        reduction_percent,
-        original_base64: encode_file(&input_path.to_string_lossy())?,
-        compressed_base64: encode_file(&output_path.to_string_lossy())?,
-    })
+    input_path: &Path,
+    output_dir: &Path,
+    options: JpegOptions,
+) -> Result<CompressionResult, CompressionFailure> {
+    compress_file(
+        &LossyCompressor { options },
+        input_path,
+        output_dir,
+        &NamingPolicy::default(),
+    )
 }
@@ -0,0 +1,236 @@
+use color_quant::NeuQuant;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::error::{Result, StrettaError};
+
+/// Palette sizes to pick from, largest first. The fewest colours that still
+/// reach `max_quality` are found by bisection, so at most four palettes are
+/// trained per image.
+const PALETTE_SIZES: [usize; 8] = [256, 128, 64, 32, 16, 8, 4, 2];
+
+/// pngquant-style palette reduction, saved as the `quantize` block of
+/// `AppSettings`. Qualities are 0 to 100 on roughly the same scale as
+/// pngquant's `--quality`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct QuantizeOptions {
+    /// Below this the image is kept at full colour and only losslessly
+    /// optimised.
+    pub min_quality: u8,
+    /// Use the fewest colours that still reach this quality.
+    pub max_quality: u8,
+    /// Floyd-Steinberg dithering from 0 (none) to 1 (full).
+    pub dithering: f32,
+    /// 1 (slowest, best palette) to 10 (fastest).
+    pub speed: u8,
+}
+
+impl Default for QuantizeOptions {
+    fn default() -> Self {
+        Self {
+            min_quality: 65,
+            max_quality: 80,
+            dithering: 1.0,
+            speed: 4,
+        }
+    }
+}
+
+impl QuantizeOptions {
+    /// Rejects quality ranges no palette can satisfy, so a batch fails up
+    /// front rather than quietly keeping every image at full colour.
+    pub fn validate(&self) -> Result<()> {
+        if self.max_quality > 100 || self.min_quality > self.max_quality {
+            return Err(StrettaError::Validation(format!(
+                "Invalid quantisation quality range: {} to {}",
+                self.min_quality, self.max_quality
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// An image reduced to at most 256 colours.
+pub struct Palettized {
+    pub palette: Vec<[u8; 4]>,
+    /// One palette index per pixel, row by row.
+    pub indices: Vec<u8>,
+    /// What the palette scored, 0 to 100.
+    pub quality: u8,
+}
+
+/// Reduces `image` to a palette, or returns `None` when the best palette
+/// still falls short of `min_quality`.
+pub fn quantize(image: &DynamicImage, options: &QuantizeOptions) -> Option<Palettized> {
+    let rgba = image.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let pixels = rgba.as_raw();
+
+    if let Some(exact) = exact_palette(pixels) {
+        return Some(exact);
+    }
+
+    // NeuQuant learns from every n-th pixel, so speed doubles as the step
+    let sample_factor = i32::from(options.speed.clamp(1, 10));
+    let max_quality = options.max_quality.min(100);
+
+    let train = |colors: usize| {
+        let quantizer = NeuQuant::new(sample_factor, colors, pixels);
+        let quality = mse_to_quality(mean_squared_error(&quantizer, pixels));
+        (quantizer, quality)
+    };
+
+    // The full palette is as good as it gets. When it reaches `max_quality`,
+    // bisect for the smallest that still does, counting on fewer colours
+    // never doing better
+    let (mut quantizer, mut quality) = train(PALETTE_SIZES[0]);
+    if quality >= max_quality {
+        let (mut reaches, mut misses) = (0, PALETTE_SIZES.len());
+        while misses - reaches > 1 {
+            let middle = (reaches + misses) / 2;
+            let candidate = train(PALETTE_SIZES[middle]);
+            if candidate.1 >= max_quality {
+                (quantizer, quality) = candidate;
+                reaches = middle;
+            } else {
+                misses = middle;
+            }
+        }
+    }
+
+    if quality < options.min_quality {
+        return None;
+    }
+
+    let palette = quantizer
+        .color_map_rgba()
+        .chunks_exact(4)
+        .map(|c| [c[0], c[1], c[2], c[3]])
+        .collect();
+    let indices = remap(
+        &quantizer,
+        pixels,
+        width as usize,
+        height as usize,
+        options.dithering,
+    );
+
+    Some(Palettized {
+        palette,
+        indices,
+        quality,
+    })
+}
+
+/// Images that already have 256 colours or fewer get a palette without any
+/// loss.
+fn exact_palette(pixels: &[u8]) -> Option<Palettized> {
+    let mut lookup: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut palette = Vec::new();
+    let mut indices = Vec::with_capacity(pixels.len() / 4);
+
+    for pixel in pixels.chunks_exact(4) {
+        let color = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        let index = match lookup.get(&color) {
+            Some(&index) => index,
+            None => {
+                let index = u8::try_from(palette.len()).ok()?;
+                lookup.insert(color, index);
+                palette.push(color);
+                index
+            }
+        };
+        indices.push(index);
+    }
+
+    Some(Palettized {
+        palette,
+        indices,
+        quality: 100,
+    })
+}
+
+/// Squared error per pixel summed over the four channels, with channels
+/// scaled to 0..1.
+fn mean_squared_error(quantizer: &NeuQuant, pixels: &[u8]) -> f64 {
+    let mut total = 0.0;
+    for pixel in pixels.chunks_exact(4) {
+        let mut mapped = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        quantizer.map_pixel(&mut mapped);
+        total += pixel
+            .iter()
+            .zip(mapped)
+            .map(|(&a, b)| ((f64::from(a) - f64::from(b)) / 255.0).powi(2))
+            .sum::<f64>();
+    }
+    total / (pixels.len() / 4).max(1) as f64
+}
+
+/// libimagequant's mapping from quality to the error it allows.
+fn quality_to_mse(quality: u8) -> f64 {
+    let quality = f64::from(quality);
+    let extra_low_quality_fudge = (0.016 / (0.001 + quality) - 0.001).max(0.0);
+    extra_low_quality_fudge + 2.5 / (210.0 + quality).powf(1.2) * (100.1 - quality) / 100.0
+}
+
+fn mse_to_quality(mse: f64) -> u8 {
+    (1..=100)
+        .rev()
+        .find(|&quality| mse <= quality_to_mse(quality) + 1e-6)
+        .unwrap_or(0)
+}
+
+/// Maps every pixel to its palette index, spreading the rounding error to
+/// the neighbours not yet visited (Floyd-Steinberg) scaled by `dithering`.
+fn remap(
+    quantizer: &NeuQuant,
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    dithering: f32,
+) -> Vec<u8> {
+    let dithering = dithering.clamp(0.0, 1.0);
+    if dithering == 0.0 {
+        return pixels
+            .chunks_exact(4)
+            .map(|pixel| quantizer.index_of(pixel) as u8)
+            .collect();
+    }
+
+    // Error carried into the current and the next row
+    let mut current = vec![[0.0f32; 4]; width + 2];
+    let mut next = vec![[0.0f32; 4]; width + 2];
+    let mut indices = Vec::with_capacity(width * height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let offset = (y * width + x) * 4;
+            let mut wanted = [0u8; 4];
+            let mut target = [0.0f32; 4];
+            for channel in 0..4 {
+                target[channel] =
+                    f32::from(pixels[offset + channel]) + current[x + 1][channel] * dithering;
+                wanted[channel] = target[channel].round().clamp(0.0, 255.0) as u8;
+            }
+
+            let index = quantizer.index_of(&wanted);
+            let chosen = quantizer.lookup(index).unwrap_or(wanted);
+            indices.push(index as u8);
+
+            for channel in 0..4 {
+                let error = target[channel] - f32::from(chosen[channel]);
+                current[x + 2][channel] += error * 7.0 / 16.0;
+                next[x][channel] += error * 3.0 / 16.0;
+                next[x + 1][channel] += error * 5.0 / 16.0;
+                next[x + 2][channel] += error / 16.0;
+            }
+        }
+
+        std::mem::swap(&mut current, &mut next);
+        next.iter_mut().for_each(|error| *error = [0.0; 4]);
+    }
+
+    indices
+}
@@ -0,0 +1,223 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, Frame, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::compressor::SourceImage;
+use crate::error::{Result, StrettaError};
+
+/// Largest side any output can have, JPEG's limit. Backends with smaller
+/// limits, like WebP's 16383, still fail the files that exceed them.
+pub const MAX_SIDE: u32 = 65_535;
+
+/// Largest `ResizeMode::Scale` percentage.
+pub const MAX_SCALE_PERCENT: f32 = 1000.0;
+
+/// Resizing done between decoding and encoding, saved as the `resize` block of
+/// `AppSettings`. Applies to every backend.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ResizeOptions {
+    pub mode: ResizeMode,
+    /// Keep images smaller than the target at their own size. `MaxSize` never
+    /// upscales either way.
+    pub never_upscale: bool,
+    pub filter: ResizeFilter,
+}
+
+impl Default for ResizeOptions {
+    fn default() -> Self {
+        Self {
+            mode: ResizeMode::None,
+            never_upscale: true,
+            filter: ResizeFilter::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ResizeMode {
+    None,
+    /// Shrinks to fit inside whichever limits are set, keeping the aspect
+    /// ratio.
+    MaxSize {
+        max_width: Option<u32>,
+        max_height: Option<u32>,
+    },
+    /// Scales both sides by a percentage.
+    Scale {
+        percent: f32,
+    },
+    /// Targets an exact size, with `fit` deciding what happens when the
+    /// aspect ratio doesn't match.
+    Exact {
+        width: u32,
+        height: u32,
+        fit: Fit,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Fit {
+    /// Fits inside the size, keeping the aspect ratio. One side can come out
+    /// shorter.
+    #[default]
+    Fit,
+    /// Stretches to the size exactly.
+    Fill,
+    /// Covers the size, keeping the aspect ratio, and crops the overflow from
+    /// the centre.
+    Crop,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    #[default]
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => Self::Nearest,
+            ResizeFilter::Triangle => Self::Triangle,
+            ResizeFilter::CatmullRom => Self::CatmullRom,
+            ResizeFilter::Gaussian => Self::Gaussian,
+            ResizeFilter::Lanczos3 => Self::Lanczos3,
+        }
+    }
+}
+
+/// Size to scale to, then the size to crop that down to from the centre.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Plan {
+    scaled: (u32, u32),
+    cropped: (u32, u32),
+}
+
+impl ResizeOptions {
+    /// Rejects sizes that can't produce an image, or that no encoder could
+    /// write, so a batch fails up front rather than on every file.
+    pub fn validate(&self) -> Result<()> {
+        let side = |length: u32| (1..=MAX_SIDE).contains(&length);
+        let valid = match self.mode {
+            ResizeMode::None => true,
+            ResizeMode::MaxSize {
+                max_width,
+                max_height,
+            } => max_width.is_none_or(side) && max_height.is_none_or(side),
+            ResizeMode::Scale { percent } => percent > 0.0 && percent <= MAX_SCALE_PERCENT,
+            ResizeMode::Exact { width, height, .. } => side(width) && side(height),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(StrettaError::Validation(format!(
+                "Invalid resize settings: {:?}",
+                self.mode
+            )))
+        }
+    }
+
+    /// Resizes the decoded image and every animation frame. Returns whether
+    /// anything changed.
+    pub fn apply(&self, source: &mut SourceImage) -> bool {
+        let Some(plan) = self.plan(source.image.width(), source.image.height()) else {
+            return false;
+        };
+
+        source.image = self.resize(&source.image, plan);
+        if let Some(animation) = &mut source.animation {
+            for frame in &mut animation.frames {
+                let buffer = DynamicImage::ImageRgba8(frame.buffer().clone());
+                let resized: RgbaImage = self.resize(&buffer, plan).into_rgba8();
+                *frame = Frame::from_parts(resized, 0, 0, frame.delay());
+            }
+        }
+        source.transformed = true;
+        true
+    }
+
+    fn resize(&self, image: &DynamicImage, plan: Plan) -> DynamicImage {
+        let (width, height) = plan.scaled;
+        let scaled = if (image.width(), image.height()) == plan.scaled {
+            image.clone()
+        } else {
+            image.resize_exact(width, height, self.filter.into())
+        };
+
+        let (crop_width, crop_height) = plan.cropped;
+        if (crop_width, crop_height) == (width, height) {
+            return scaled;
+        }
+        scaled.crop_imm(
+            (width - crop_width) / 2,
+            (height - crop_height) / 2,
+            crop_width,
+            crop_height,
+        )
+    }
+
+    /// `None` when the image already has the target size.
+    fn plan(&self, width: u32, height: u32) -> Option<Plan> {
+        let (w, h) = (f64::from(width), f64::from(height));
+        let limit = |scale: f64| {
+            if self.never_upscale {
+                scale.min(1.0)
+            } else {
+                scale
+            }
+        };
+
+        let (scale_x, scale_y, target) = match self.mode {
+            ResizeMode::None => return None,
+            ResizeMode::MaxSize {
+                max_width,
+                max_height,
+            } => {
+                let scale_for =
+                    |max: Option<u32>, side: f64| max.map_or(1.0, |m| f64::from(m) / side);
+                let scale = scale_for(max_width, w)
+                    .min(scale_for(max_height, h))
+                    .min(1.0);
+                (scale, scale, None)
+            }
+            ResizeMode::Scale { percent } => {
+                let scale = limit(f64::from(percent) / 100.0);
+                (scale, scale, None)
+            }
+            ResizeMode::Exact {
+                width: target_width,
+                height: target_height,
+                fit,
+            } => {
+                let (tw, th) = (f64::from(target_width), f64::from(target_height));
+                match fit {
+                    Fit::Fit => {
+                        let scale = limit((tw / w).min(th / h));
+                        (scale, scale, None)
+                    }
+                    Fit::Fill => (limit(tw / w), limit(th / h), None),
+                    Fit::Crop => {
+                        let scale = limit((tw / w).max(th / h));
+                        (scale, scale, Some((target_width, target_height)))
+                    }
+                }
+            }
+        };
+
+        // Cropping after covering a very different aspect ratio can still
+        // overshoot, so no side ever goes past what an encoder can write
+        let side = |length: f64, scale: f64| ((length * scale).round() as u32).clamp(1, MAX_SIDE);
+        let scaled = (side(w, scale_x), side(h, scale_y));
+        let cropped = target.map_or(scaled, |(tw, th)| (tw.min(scaled.0), th.min(scaled.1)));
+
+        (scaled != (width, height) || cropped != scaled).then_some(Plan { scaled, cropped })
+    }
+}
@@ -1,13 +1,31 @@
 use std::fs;
-use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use base64::prelude::*;
 use serde::{Serialize, Deserialize};
-use crate::webp_compressor::webp_compression;
-use crate::lossy_compressor::lossy_compression;
-use crate::lossless_compressor::lossless_compression;
 use base64::{engine::general_purpose};
+use crate::alpha::{AlphaAction, AlphaOptions};
+use crate::avif_compressor::AvifOptions;
+use crate::error::{Result, StrettaError};
+use crate::gif_compressor::GifOptions;
+use crate::jpeg_optimizer::LosslessJpegOptions;
+use crate::jxl_compressor::JxlOptions;
+use crate::lossless_compressor::PngOptions;
+use crate::lossy_compressor::JpegOptions;
+use crate::png_quantizer::QuantizeOptions;
+use crate::resize::ResizeOptions;
+use crate::webp_compressor::WebpOptions;
+#[cfg(feature = "gui")]
+use {
+    crate::diagnostics::log_line,
+    crate::job::{BatchControl, BatchReport, CompressionJob, ProgressEvent},
+    std::io::Write,
+    std::sync::Arc,
+    tauri::{AppHandle, Emitter, Manager, State},
+};
+
+/// Event the compression commands emit for every `ProgressEvent` of a batch.
+pub const PROGRESS_EVENT: &str = "compression-progress";
 
 
 
@@ -18,26 +36,112 @@ pub struct CompressionResult {
     pub original_size: u64,
     pub compressed_size: u64,
     pub reduction_percent: f32,
-    pub original_base64: String,
-    pub compressed_base64: String,
+    /// Frames in the output, 1 for still images.
+    pub frame_count: u32,
+    /// Pixel size of the output, after any resizing.
+    pub width: u32,
+    pub height: u32,
+    /// What was done about transparency the output format couldn't keep,
+    /// `None` when there was nothing to do.
+    pub alpha_action: Option<AlphaAction>,
+    /// Both files base64-encoded for the results page. Only the GUI fills
+    /// these in, see `run_app_job`.
+    pub original_base64: Option<String>,
+    pub compressed_base64: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Pipeline step an image failed at.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureStage {
+    Decode,
+    Encode,
+    Write,
+}
+
+/// An input that couldn't be compressed, reported alongside the successful
+/// `CompressionResult`s so the UI can show what failed and why.
+#[derive(Serialize, Debug, Clone)]
+pub struct CompressionFailure {
+    pub file_name: String,
+    pub stage: FailureStage,
+    pub error: String,
+}
+
+impl CompressionFailure {
+    pub fn new(input_path: &Path, stage: FailureStage, error: impl Into<String>) -> Self {
+        Self {
+            file_name: input_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| input_path.display().to_string()),
+            stage,
+            error: error.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for CompressionFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} failed to {:?}: {}", self.file_name, self.stage, self.error)
+    }
+}
+
+/// What each method writes for each input format. JPEG inputs are never
+/// turned into PNG or WebP, which would only make photos bigger.
+///
+/// | Input             | Lossy | Lossless        | QuantizedPng    | Gif            | WebpLossy      | WebpLossless     | Avif | JpegXl          |
+/// |-------------------|-------|-----------------|-----------------|----------------|----------------|------------------|------|-----------------|
+/// | JPEG              | JPEG  | JPEG (lossless) | JPEG (lossless) | skipped        | JPEG           | JPEG             | AVIF | JXL (lossless)  |
+/// | GIF               | JPEG  | PNG             | PNG (palette)   | GIF (animated) | WebP (lossy)   | WebP (lossless)  | AVIF | JXL             |
+/// | PNG/BMP           | JPEG  | PNG             | PNG (palette)   | skipped        | WebP (lossy)   | WebP (lossless)  | AVIF | JXL             |
+/// | WebP              | JPEG  | WebP (lossless) | PNG (palette)   | skipped        | WebP (lossy)   | WebP (lossless)  | AVIF | JXL             |
+///
+/// "JPEG (lossless)" keeps the JPEG's coefficients and only rewrites how
+/// they're stored, so the pixels don't change. WebP under `Lossless` is
+/// either re-encoded losslessly or kept as it was minus EXIF and XMP.
+///
+/// JPEG XL needs the `jxl` feature; without it `JpegXl` has no backend.
+///
+/// `KeepFormat` writes JPEG, PNG, GIF and WebP inputs back in the format their
+/// contents really are, JPEGs losslessly, lossy WebP as lossy WebP and
+/// lossless as lossless, and keeps the original when that comes out smaller.
+/// It skips BMP.
+///
+/// Once `AppSettings::resize` changes an image's size, nothing is passed
+/// through as it was: JPEGs under `Lossless`, `QuantizedPng` and `KeepFormat`
+/// are re-encoded as JPEG at the highest quality, `JpegXl` encodes JPEGs from
+/// their pixels and `KeepFormat` no longer falls back to the original.
+///
+/// Transparent inputs under `Lossy` are handled as `AppSettings::alpha` says:
+/// flattened onto a background colour, kept as they are, or compressed with
+/// another method that keeps transparency.
+///
+/// Animated GIF, PNG and WebP inputs stay animated under `Gif` and the WebP
+/// methods, keeping their frame timing and loop count. `Lossless` and
+/// `QuantizedPng` write them as full-colour APNG, except that `Lossless`
+/// keeps animated WebP as WebP. Every other method writes the first frame.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum CompressionMethod {
     #[serde(rename = "lossy")]
     Lossy,
     #[serde(rename = "lossless")]
     Lossless,
+    #[serde(rename = "quantized_png")]
+    QuantizedPng,
+    #[serde(rename = "gif")]
+    Gif,
     #[serde(rename = "webp_lossy")]
+    #[default]
     WebpLossy,
     #[serde(rename = "webp_lossless")]
     WebpLossless,
-}
-
-impl Default for CompressionMethod {
-    fn default() -> Self {
-        Self::WebpLossy
-    }
+    #[serde(rename = "avif")]
+    Avif,
+    #[serde(rename = "jpeg_xl")]
+    JpegXl,
+    #[serde(rename = "keep_format")]
+    KeepFormat,
 }
 
 impl CompressionMethod {
@@ -45,8 +149,32 @@ impl CompressionMethod {
         match self {
             Self::Lossy => "lossy",
             Self::Lossless => "lossless",
+            Self::QuantizedPng => "quantized_png",
+            Self::Gif => "gif",
             Self::WebpLossy => "webp_lossy",
             Self::WebpLossless => "webp_lossless",
+            Self::Avif => "avif",
+            Self::JpegXl => "jpeg_xl",
+            Self::KeepFormat => "keep_format",
+        }
+    }
+}
+
+impl std::str::FromStr for CompressionMethod {
+    type Err = StrettaError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "lossy" => Ok(Self::Lossy),
+            "lossless" => Ok(Self::Lossless),
+            "quantized_png" => Ok(Self::QuantizedPng),
+            "gif" => Ok(Self::Gif),
+            "webp_lossy" => Ok(Self::WebpLossy),
+            "webp_lossless" => Ok(Self::WebpLossless),
+            "avif" => Ok(Self::Avif),
+            "jpeg_xl" => Ok(Self::JpegXl),
+            "keep_format" => Ok(Self::KeepFormat),
+            _ => Err(StrettaError::Settings(format!("Unknown compression method: {}", s))),
         }
     }
 }
@@ -55,6 +183,26 @@ impl CompressionMethod {
 pub struct AppSettings {
     pub compression_quality: f32,
     pub method: CompressionMethod,
+    #[serde(default)]
+    pub jpeg: JpegOptions,
+    #[serde(default)]
+    pub webp: WebpOptions,
+    #[serde(default)]
+    pub lossless_jpeg: LosslessJpegOptions,
+    #[serde(default)]
+    pub png: PngOptions,
+    #[serde(default)]
+    pub quantize: QuantizeOptions,
+    #[serde(default)]
+    pub gif: GifOptions,
+    #[serde(default)]
+    pub avif: AvifOptions,
+    #[serde(default)]
+    pub jxl: JxlOptions,
+    #[serde(default)]
+    pub alpha: AlphaOptions,
+    #[serde(default)]
+    pub resize: ResizeOptions,
 }
 
 impl Default for AppSettings {
@@ -62,6 +210,16 @@ impl Default for AppSettings {
         Self {
             compression_quality: 75.0,
             method: CompressionMethod::WebpLossy,
+            jpeg: JpegOptions::default(),
+            webp: WebpOptions::default(),
+            lossless_jpeg: LosslessJpegOptions::default(),
+            png: PngOptions::default(),
+            quantize: QuantizeOptions::default(),
+            gif: GifOptions::default(),
+            avif: AvifOptions::default(),
+            jxl: JxlOptions::default(),
+            alpha: AlphaOptions::default(),
+            resize: ResizeOptions::default(),
         }
     }
 }
@@ -74,61 +232,92 @@ pub static SETTINGS_DIR: OnceLock<PathBuf> = OnceLock::new();
 
 
 // Public functions to access the global paths
-pub fn get_input_path() -> &'static PathBuf {
-    INPUT_PATH.get().expect("Input path not initialized")
+pub fn get_input_path() -> Result<&'static PathBuf> {
+    INPUT_PATH
+        .get()
+        .ok_or_else(|| StrettaError::Settings("Input path not initialized".to_string()))
 }
 
-pub fn get_output_path() -> &'static PathBuf {
-    OUTPUT_PATH.get().expect("Output path not initialized")
+pub fn get_output_path() -> Result<&'static PathBuf> {
+    OUTPUT_PATH
+        .get()
+        .ok_or_else(|| StrettaError::Settings("Output path not initialized".to_string()))
 }
 
 // Initialize the global paths (called during setup)
-pub fn initialize_image_paths(app_data_dir: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+pub fn initialize_image_paths(app_data_dir: PathBuf) -> Result<()> {
     let input_dir = app_data_dir.join("images/input");
     let output_dir = app_data_dir.join("images/output");
 
     // Create directories if they don't exist
-    fs::create_dir_all(&input_dir)?;
-    fs::create_dir_all(&output_dir)?;
+    fs::create_dir_all(&input_dir).map_err(|e| StrettaError::io("Failed to create input dir", e))?;
+    fs::create_dir_all(&output_dir).map_err(|e| StrettaError::io("Failed to create output dir", e))?;
 
     // Set the global paths
     crate::utility::INPUT_PATH
         .set(input_dir)
-        .map_err(|_| "Failed to set input path")?;
+        .map_err(|_| StrettaError::Settings("Failed to set input path".to_string()))?;
     crate::utility::OUTPUT_PATH
         .set(output_dir)
-        .map_err(|_| "Failed to set output path")?;
+        .map_err(|_| StrettaError::Settings("Failed to set output path".to_string()))?;
 
     Ok(())
 }
 
-pub fn clear_input_folder() -> Result<(), String> {
-    let input_path = get_input_path();
+pub fn clear_input_folder() -> Result<()> {
+    clear_folder(get_input_path()?)
+}
+
+pub fn clear_output_folder() -> Result<()> {
+    clear_folder(get_output_path()?)
+}
+
+/// Builds a job over the app's input folder, writing into a freshly cleared
+/// output folder. This is the only place the compression commands touch the
+/// global paths; the pipeline itself works on the returned job.
+#[cfg(feature = "gui")]
+pub fn app_compression_job(app: &AppHandle, settings: AppSettings) -> Result<CompressionJob> {
+    clear_output_folder()?;
+    let mut job = CompressionJob::from_directory(get_input_path()?, get_output_path()?.clone(), settings)?;
 
-    // Clear the input folder
-    fs::remove_dir_all(&input_path)
-        .map_err(|e| e.to_string())
-        .and_then(|_| {
-            // Recreate the input folder
-            fs::create_dir_all(&input_path).map_err(|e| e.to_string())
-        })
-        .map(|_| ())
+    // Share the app-wide control so the cancel/pause commands reach this batch
+    job.control = app.state::<Arc<BatchControl>>().inner().clone();
+
+    Ok(job)
 }
 
-pub fn clear_output_folder() -> Result<(), String> {
-    let output_path = get_output_path();
+/// Runs `settings` over the app's input folder, reporting progress to the
+/// frontend and attaching the previews the results page shows.
+#[cfg(feature = "gui")]
+pub fn run_app_job(app: &AppHandle, settings: AppSettings) -> Result<BatchReport> {
+    let mut report = app_compression_job(app, settings)?.run_with_progress(&emit_progress(app))?;
+    for result in &mut report.completed {
+        result.original_base64 = Some(encode_file(&result.original_path)?);
+        result.compressed_base64 = Some(encode_file(&result.compressed_path)?);
+    }
+    Ok(report)
+}
 
-    // Clear the output folder
-    fs::remove_dir_all(&output_path)
-        .map_err(|e| e.to_string())
-        .and_then(|_| {
-            // Recreate the output folder
-            fs::create_dir_all(&output_path).map_err(|e| e.to_string())
-        })
-        .map(|_| ())
+/// Clears the last batch's cancel or pause. Commands that start a batch call
+/// this before anything else, so a cancel that arrives while the inputs are
+/// still being copied in isn't lost.
+#[cfg(feature = "gui")]
+pub fn start_app_batch(app: &AppHandle) {
+    app.state::<Arc<BatchControl>>().reset();
 }
 
-pub fn is_jpeg(path: &PathBuf) -> bool {
+/// Removes everything inside `path`, leaving an empty directory behind.
+pub fn clear_folder(path: &Path) -> Result<()> {
+    if path.exists() {
+        fs::remove_dir_all(path)
+            .map_err(|e| StrettaError::io(format!("Failed to clear {}", path.display()), e))?;
+    }
+    // Recreate the folder
+    fs::create_dir_all(path)
+        .map_err(|e| StrettaError::io(format!("Failed to create {}", path.display()), e))
+}
+
+pub fn is_jpeg(path: &Path) -> bool {
     path.extension()
         .and_then(|e| e.to_str())
         .map(|ext| ext.eq_ignore_ascii_case("jpg") || ext.eq_ignore_ascii_case("jpeg"))
@@ -147,7 +336,6 @@ pub fn deduplicate_path(base_path: &Path) -> PathBuf {
     let parent = base_path.parent().unwrap_or_else(|| Path::new(""));
     let stem = base_path.file_stem().unwrap().to_string_lossy();
     let ext = base_path.extension().map(|e| e.to_string_lossy()).unwrap_or_default();
-    //let ext = "jpg"; // Assuming JPEG for compression, adjust as needed
 
     for i in 1.. {
         let new_file_name = if ext.is_empty() {
@@ -165,75 +353,99 @@ pub fn deduplicate_path(base_path: &Path) -> PathBuf {
     unreachable!("deduplicate_path ran out of integer suffixes")
 }
 
-pub fn initialize_settings_path(app_data_dir: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+pub fn initialize_settings_path(app_data_dir: PathBuf) -> Result<()> {
     let settings_path = app_data_dir.join("settings");
     // Create the app data directory if it doesn't exist
-    fs::create_dir_all(&settings_path).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&settings_path).map_err(|e| StrettaError::io("Failed to create settings dir", e))?;
 
         // Set the global paths
     crate::utility::SETTINGS_DIR
         .set(app_data_dir.clone())
-        .map_err(|_| "Failed to set input path")?;
+        .map_err(|_| StrettaError::Settings("Failed to set settings path".to_string()))?;
 
     Ok(())
 }
 
-fn get_settings_path() -> std::path::PathBuf {
-    SETTINGS_DIR.get()
-        .expect("Settings directory not initialized")
-        .join("settings.json")
+pub fn get_settings_dir() -> Result<&'static PathBuf> {
+    SETTINGS_DIR
+        .get()
+        .ok_or_else(|| StrettaError::Settings("Settings directory not initialized".to_string()))
 }
 
-#[tauri::command]
-pub fn load_settings() -> Result<AppSettings, String> {
-    let path = get_settings_path();
+fn get_settings_path() -> Result<PathBuf> {
+    Ok(get_settings_dir()?.join("settings.json"))
+}
+
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn load_settings() -> Result<AppSettings> {
+    let path = get_settings_path()?;
     if !path.exists() {
         return Ok(AppSettings::default());
     }
-    let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let settings = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    let content = std::fs::read_to_string(&path).map_err(|e| StrettaError::io("Failed to read settings", e))?;
+    let settings = serde_json::from_str(&content).map_err(|e| StrettaError::Settings(e.to_string()))?;
     Ok(settings)
 }
 
-#[tauri::command]
-pub fn save_settings(settings: AppSettings) -> Result<(), String> {
-    let path = get_settings_path();
-    let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
-    std::fs::write(&path, content).map_err(|e| e.to_string())
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn save_settings(settings: AppSettings) -> Result<()> {
+    let path = get_settings_path()?;
+    let content = serde_json::to_string_pretty(&settings).map_err(|e| StrettaError::Settings(e.to_string()))?;
+    std::fs::write(&path, content).map_err(|e| StrettaError::io("Failed to write settings", e))
 }
 
-#[tauri::command]
-pub async fn handle_compression() -> Result<Vec<CompressionResult>, String> {
-    let settings = load_settings().unwrap_or_default();
-    let mut results: Vec<CompressionResult> = Vec::new();
-
-    if settings.method ==  CompressionMethod::WebpLossy || settings.method == CompressionMethod::WebpLossless {
-        // run WebP compression
-        println!("Running WebP compression with quality: {} and method: {}", settings.compression_quality, settings.method.as_str());
-        if CompressionMethod::WebpLossy == settings.method {
-            results = webp_compression(settings.method == CompressionMethod::WebpLossy, settings.compression_quality).expect("WebP compression failed");
-            println!("Running lossy WebP compression");
-        } else if CompressionMethod::WebpLossless == settings.method {
-            results = webp_compression(settings.method == CompressionMethod::WebpLossless, settings.compression_quality).expect("WebP compression failed");
-            println!("Running lossless WebP compression");
+/// Forwards pipeline progress to the frontend as `PROGRESS_EVENT` events.
+#[cfg(feature = "gui")]
+pub fn emit_progress(app: &AppHandle) -> impl Fn(ProgressEvent) + Sync + '_ {
+    move |event| {
+        if let Err(e) = app.emit(PROGRESS_EVENT, event) {
+            log_line!("Failed to emit progress event: {}", e);
         }
-        //webp_compression(settings.method == CompressionMethod::WebpLossless, settings.compression_quality).expect("WebP compression failed");
-    } else if settings.method == CompressionMethod::Lossy {
-        // run JPEG compression
-        println!("Running lossy compression with quality: {}", settings.compression_quality);
-        results = lossy_compression().expect("Lossy compression failed");
-    } else if settings.method == CompressionMethod::Lossless {
-        // run PNG compression
-        println!("Running lossless compression");
-        results = lossless_compression().expect("Lossless compression failed");
-    } else {
-        println!("Error: Unknown compression method");    
     }
-    println!("Compression completed. Here are the results: {:?}", results);
-    if results.is_empty() {
-        return Err("No images were processed".to_string());
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn handle_compression(app: AppHandle) -> Result<BatchReport> {
+    start_app_batch(&app);
+    ensure_processed(run_app_compression(&app)?)
+}
+
+#[cfg(feature = "gui")]
+fn run_app_compression(app: &AppHandle) -> Result<BatchReport> {
+    let settings = load_settings().unwrap_or_default();
+
+    log_line!("Running {} compression with quality: {}", settings.method.as_str(), settings.compression_quality);
+    run_app_job(app, settings)
+}
+
+#[cfg(feature = "gui")]
+fn ensure_processed(report: BatchReport) -> Result<BatchReport> {
+    if report.is_empty() {
+        return Err(StrettaError::Validation("No images were processed".to_string()));
     }
-    Ok(results)
+    Ok(report)
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn cancel_compression(control: State<'_, Arc<BatchControl>>) {
+    log_line!("Cancelling compression batch");
+    control.cancel();
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn pause_compression(control: State<'_, Arc<BatchControl>>) {
+    log_line!("Pausing compression batch");
+    control.pause();
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn resume_compression(control: State<'_, Arc<BatchControl>>) {
+    log_line!("Resuming compression batch");
+    control.resume();
 }
 
 
@@ -246,64 +458,65 @@ pub struct ImageData {
     pub filename: String,
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub async fn handle_images(images: Vec<ImageData>) -> Result<Vec<CompressionResult>, String> {
+pub async fn handle_images(app: AppHandle, images: Vec<ImageData>) -> Result<BatchReport> {
+    start_app_batch(&app);
 
-    println!("handle_images function called with {} images", images.len());
+    log_line!("handle_images function called with {} images", images.len());
     // Get the global input path
-    let source = get_input_path();
+    let source = get_input_path()?;
 
     // Clear the input folder before processing new images
-    clear_input_folder().map_err(|e| format!("Failed to clear input folder {}", e))?;
+    clear_input_folder()?;
+    let mut rejected = Vec::new();
 
     for (i, image_data) in images.iter().enumerate() {
         // Strip the base64 header
         let base64_str = image_data.data
             .split(',')
             .nth(1)
-            .ok_or("Invalid base64 image format")?;
+            .ok_or_else(|| StrettaError::Validation("Invalid base64 image format".to_string()))?;
 
         let decoded_bytes = BASE64_STANDARD
             .decode(base64_str)
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| StrettaError::Validation(format!("Invalid base64 image data: {}", e)))?;
 
         // Extract original filename and create new names
         let original_name = &image_data.filename;
-        // let file_stem = std::path::Path::new(original_name)
-        //     .file_stem()
-        //     .and_then(|s| s.to_str())
-        //     .unwrap_or("image");
         
-        println!("Processing image[{}]: {} ({} bytes)", i, original_name, decoded_bytes.len());
+        log_line!("Processing image[{}]: {} ({} bytes)", i, original_name, decoded_bytes.len());
         
         // Validate image data before proceeding
         if let Err(validation_error) = validate_image_data(&decoded_bytes, original_name) {
-            println!("Skipping invalid image {}: {}", original_name, validation_error);
+            log_line!("Skipping invalid image {}: {}", original_name, validation_error);
+            rejected.push(CompressionFailure::new(Path::new(original_name), FailureStage::Decode, validation_error.to_string()));
             continue; // Skip this image but continue with others
         }
         
         // Store original with original extension for input
-        let input_filename = format!("{}", original_name);
+        let input_filename = original_name.to_string();
         let input_path = source.join(&input_filename);
 
-        let mut file = fs::File::create(&input_path).map_err(|e| e.to_string())?;
-        file.write_all(&decoded_bytes).map_err(|e| e.to_string())?;
+        let mut file = fs::File::create(&input_path).map_err(|e| StrettaError::io("Failed to create input file", e))?;
+        file.write_all(&decoded_bytes).map_err(|e| StrettaError::io("Failed to write input file", e))?;
         
-        println!("Created input file: {:?}", input_path);
+        log_line!("Created input file: {:?}", input_path);
     }
 
     //compress images
-    println!("Starting compression process... (handle_compression is called)");
-    let results = handle_compression().await.unwrap();
-    //crate::parallel_compressor::parallel_compress();
+    log_line!("Starting compression process... (handle_compression is called)");
+    let mut report = run_app_compression(&app)?;
+    report.failed.extend(rejected);
 
-    Ok(results)
+    ensure_processed(report)
 }
 
-fn validate_image_data(data: &[u8], filename: &str) -> Result<(), String> {
+#[cfg(feature = "gui")]
+fn validate_image_data(data: &[u8], filename: &str) -> Result<()> {
     // Basic validation - check if the data looks like an image
     if data.is_empty() {
-        return Err(format!("Image data is empty for {}", filename));
+        return Err(StrettaError::Validation(format!("Image data is empty for {}", filename)));
     }
     
     // Check for common image file signatures
@@ -319,44 +532,19 @@ fn validate_image_data(data: &[u8], filename: &str) -> Result<(), String> {
         _ => {
             // If we can't identify the format, let's still try to process it
             // The image_compressor library might handle it
-            println!("Warning: Unknown image format for {}, attempting to process anyway", filename);
+            log_line!("Warning: Unknown image format for {}, attempting to process anyway", filename);
             true
         }
     };
     
     if !is_valid {
-        return Err(format!("Invalid image format detected for {}", filename));
+        return Err(StrettaError::Validation(format!("Invalid image format detected for {}", filename)));
     }
     
     Ok(())
 }
 
-pub fn encode_file(path: &str) -> Result<String, String> {
-    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+pub fn encode_file(path: &str) -> Result<String> {
+    let bytes = std::fs::read(path).map_err(|e| StrettaError::io("Failed to read file", e))?;
     Ok(general_purpose::STANDARD.encode(&bytes))
 }
-
-// fn analyze_compression_results(input_count: usize, output_count: usize) -> String {
-//     if input_count == output_count {
-//         format!("✅ All {} images compressed successfully", input_count)
-//     } else if output_count == 0 {
-//         "❌ No images were compressed. Possible causes:
-//         - All input images were corrupted or invalid
-//         - Unsupported image formats
-//         - Insufficient disk space
-//         - Permission issues with output folder".to_string()
-//     } else {
-//         format!("⚠️  Partial compression: {} out of {} images compressed successfully.
-        
-//         Possible causes for failed images:
-//         - Corrupted image data
-//         - Unsupported image formats (some exotic formats may not be supported)
-//         - Images with unusual dimensions or color profiles
-//         - Memory limitations for very large images
-//         - File system errors during processing
-        
-//         Successfully compressed: {}
-//         Failed to compress: {}", 
-//         output_count, input_count, output_count, input_count - output_count)
-//     }
-// }
\ No newline at end of file
@@ -1,108 +1,312 @@
-use crate::lossy_compressor::compress_image_lossy;
+use crate::animation::Animation;
+use crate::compressor::{Capabilities, Compressor, SourceImage, compress_file};
+use crate::error::{Result, StrettaError};
+use crate::job::NamingPolicy;
 use crate::utility::{
-    clear_output_folder, deduplicate_path, get_input_path, get_output_path, is_jpeg,
-    CompressionResult, encode_file,
+    AppSettings, CompressionFailure, CompressionMethod, CompressionResult, is_jpeg,
+};
+use image::metadata::Orientation;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use webp::{AnimEncoder, AnimFrame, Encoder, WebPConfig};
+#[cfg(feature = "gui")]
+use {
+    crate::diagnostics::log_line,
+    crate::job::BatchReport,
+    crate::utility::{load_settings, run_app_job, start_app_batch},
+    tauri::AppHandle,
 };
-use image::GenericImageView;
-use image::ImageReader;
-use rayon::prelude::*;
-use std::fs;
-use std::path::{PathBuf};
-use webp::Encoder;
 
-#[tauri::command]
-pub fn webp_compression(lossless: bool, quality: f32) -> Result<Vec<CompressionResult>, String> {
-    println!("WebP compression function called.");
-
-    let input_dir = get_input_path();
-    let output_dir = get_output_path();
-
-    clear_output_folder().map_err(|e| format!("Failed to clear output folder: {}", e))?;
-    fs::create_dir_all(&output_dir).map_err(|e| format!("Failed to create output dir: {}", e))?;
-
-    let input_files: Vec<PathBuf> = fs::read_dir(&input_dir)
-        .map_err(|e| format!("Failed to read input dir: {}", e))?
-        .filter_map(|res| res.ok())
-        .map(|entry| entry.path())
-        .filter(|p| p.is_file())
-        .collect();
-
-    let results: Vec<CompressionResult> = input_files
-        .par_iter()
-        .filter_map(|input| {
-            if is_jpeg(input) {
-                compress_image_lossy(input, &output_dir).ok()
-            } else {
-                compress_to_webp(input, &output_dir, quality, lossless).ok()
-            }
-        })
-        .collect();
+/// WebP output through libwebp. JPEG inputs go through mozjpeg instead, which
+/// usually beats WebP on photos that are already JPEG. Animated GIF, PNG and
+/// WebP inputs become animated WebP.
+pub struct WebpCompressor {
+    pub options: WebpOptions,
+}
 
-    println!(
-        "Webp compression completed. {} files processed.",
-        results.len()
-    );
+/// Knobs for libwebp's advanced `WebPConfig` API, saved as the `webp` block of
+/// `AppSettings`. Ranges follow libwebp's own.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WebpOptions {
+    /// 0 to 100. Not saved with the block; it always comes from
+    /// `AppSettings::compression_quality`.
+    #[serde(skip)]
+    pub quality: f32,
+    /// Picked by the compression method rather than saved with the block.
+    #[serde(skip)]
+    pub lossless: bool,
+    /// Effort from 0 (fast) to 6 (slowest, smallest).
+    pub method: u8,
+    /// Spatial noise shaping, 0 to 100. Lossy only.
+    pub sns_strength: u8,
+    /// Deblocking filter, 0 (off) to 100. Lossy only.
+    pub filter_strength: u8,
+    /// Number of segments, 1 to 4. Lossy only.
+    pub segments: u8,
+    /// 0 to 100, where 100 keeps lossless output truly lossless. Lower values
+    /// let libwebp adjust pixel values to save space.
+    pub near_lossless: u8,
+    /// Quality of the alpha plane in lossy output, 0 to 100.
+    pub alpha_quality: u8,
+    /// Keep RGB values under fully transparent pixels instead of letting
+    /// libwebp clean them up.
+    pub exact: bool,
+}
 
-    Ok(results)
+impl Default for WebpOptions {
+    fn default() -> Self {
+        Self {
+            quality: 75.0,
+            lossless: false,
+            method: 4,
+            sns_strength: 50,
+            filter_strength: 60,
+            segments: 4,
+            near_lossless: 100,
+            alpha_quality: 100,
+            exact: false,
+        }
+    }
 }
 
-pub fn compress_to_webp(
-    input_path: &PathBuf,
-    output_dir: &PathBuf,
-    quality: f32,
-    lossless: bool,
-) -> Result<CompressionResult, String> {
-    // Load the image using the image crate
-    let img = ImageReader::open(input_path)
-        .map_err(|e| format!("Failed to open image: {}", e))?
-        .decode()
-        .map_err(|e| format!("Failed to decode image: {}", e))?;
-
-    let original_size = fs::metadata(input_path).map(|m| m.len()).unwrap_or(0);
-
-    // Prepare image buffer
-    let rgba = img.to_rgba8(); // ensures alpha is preserved
-    let (width, height) = img.dimensions();
-
-    // Encode with WebP
-    let encoded = if lossless {
-        Encoder::from_rgba(&rgba, width, height).encode_lossless()
-    } else {
-        Encoder::from_rgba(&rgba, width, height).encode(quality)
+impl WebpOptions {
+    pub fn from_settings(settings: &AppSettings, lossless: bool) -> Self {
+        Self {
+            quality: settings.compression_quality,
+            lossless,
+            ..settings.webp.clone()
+        }
+    }
+
+    fn config(&self) -> Result<WebPConfig> {
+        let mut config = WebPConfig::new()
+            .map_err(|_| StrettaError::Encode("Failed to initialise WebPConfig".to_string()))?;
+
+        config.lossless = i32::from(self.lossless);
+        if self.lossless {
+            // For lossless output libwebp reads quality as effort, so keep its default
+            config.alpha_compression = 0;
+        } else {
+            config.quality = self.quality.clamp(0.0, 100.0);
+            config.alpha_compression = 1;
+        }
+
+        config.method = i32::from(self.method.min(6));
+        config.sns_strength = i32::from(self.sns_strength.min(100));
+        config.filter_strength = i32::from(self.filter_strength.min(100));
+        config.segments = i32::from(self.segments.clamp(1, 4));
+        config.near_lossless = i32::from(self.near_lossless.min(100));
+        config.alpha_quality = i32::from(self.alpha_quality.min(100));
+        config.exact = i32::from(self.exact);
+
+        Ok(config)
+    }
+}
+
+impl Compressor for WebpCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            lossless: self.options.lossless,
+            alpha: true,
+            animation: true,
+        }
+    }
+
+    fn default_extension(&self) -> &'static str {
+        "webp"
+    }
+
+    fn accepts(&self, input_path: &Path) -> bool {
+        !is_jpeg(input_path)
+    }
+
+    fn fallback(&self) -> Option<CompressionMethod> {
+        Some(CompressionMethod::Lossy)
+    }
+
+    fn encode(&self, source: &SourceImage) -> Result<Vec<u8>> {
+        let config = self.options.config()?;
+        if let Some(animation) = &source.animation {
+            return encode_animation(animation, &config);
+        }
+
+        let rgba = source.image.to_rgba8(); // ensures alpha is preserved
+        let (width, height) = rgba.dimensions();
+
+        let encoded = Encoder::from_rgba(&rgba, width, height)
+            .encode_advanced(&config)
+            .map_err(|e| StrettaError::Encode(format!("libwebp failed: {:?}", e)))?;
+
+        Ok(encoded.to_vec())
+    }
+}
+
+/// Encodes every frame with libwebp's animation encoder, which also works out
+/// the sub-rectangles and blending between frames.
+fn encode_animation(animation: &Animation, config: &WebPConfig) -> Result<Vec<u8>> {
+    let (width, height) = animation.canvas_size();
+    let mut encoder = AnimEncoder::new(width, height, config);
+    encoder.set_loop_count(i32::try_from(animation.loop_count).unwrap_or(0));
+
+    // libwebp wants each frame's start time rather than its duration
+    let mut timestamp_ms = 0.0f64;
+    let mut last_duration_ms = 0.0;
+    for frame in &animation.frames {
+        encoder.add_frame(AnimFrame::from_rgba(
+            frame.buffer().as_raw(),
+            width,
+            height,
+            timestamp_ms.round() as i32,
+        ));
+        let (numerator, denominator) = frame.delay().numer_denom_ms();
+        last_duration_ms = f64::from(numerator) / f64::from(denominator.max(1));
+        timestamp_ms += last_duration_ms;
+    }
+
+    let mut encoded = encoder
+        .try_encode()
+        .map_err(|e| StrettaError::Encode(format!("libwebp animation failed: {:?}", e)))?
+        .to_vec();
+    set_last_frame_duration(&mut encoded, last_duration_ms.round() as u32);
+    Ok(encoded)
+}
+
+/// The webp crate ends the animation at timestamp 0, which leaves libwebp
+/// guessing how long the last frame lasts, so write it into the last `ANMF`
+/// chunk afterwards.
+fn set_last_frame_duration(webp: &mut [u8], duration_ms: u32) {
+    // Chunks follow the 12-byte RIFF header and are padded to even sizes
+    let mut offset = 12;
+    let mut last_frame = None;
+    while let Some(header) = webp.get(offset..offset + 8) {
+        let size = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        if &header[..4] == b"ANMF" {
+            last_frame = Some(offset + 8);
+        }
+        offset += 8 + size + (size & 1);
+    }
+
+    // The 24-bit duration sits after the frame's offset and size
+    if let Some(duration) = last_frame.and_then(|at| webp.get_mut(at + 12..at + 15)) {
+        duration.copy_from_slice(&duration_ms.min(0xFF_FFFF).to_le_bytes()[..3]);
+    }
+}
+
+/// Lossless re-optimisation of a WebP input for the lossless method. Tries a
+/// lossless re-encode and the original with its EXIF and XMP dropped, and
+/// keeps whichever is smaller. Lossy inputs usually keep their own bitstream
+/// that way, which is still free of any further loss. Resized and rotated
+/// inputs can only be re-encoded.
+pub fn optimize_webp(source: &SourceImage, options: &WebpOptions) -> Result<Vec<u8>> {
+    let options = WebpOptions {
+        lossless: true,
+        // Near-lossless would quietly change pixels
+        near_lossless: 100,
+        ..options.clone()
     };
+    let reencoded = WebpCompressor { options }.encode(source)?;
+
+    // Stripping EXIF would also drop the orientation the pixels need
+    if source.transformed || source.orientation != Orientation::NoTransforms {
+        return Ok(reencoded);
+    }
+    Ok(match strip_metadata(&source.bytes) {
+        Some(stripped) if stripped.len() <= reencoded.len() => stripped,
+        _ => reencoded,
+    })
+}
+
+/// Re-muxes a WebP file without its `EXIF` and `XMP ` chunks, clearing their
+/// flags in `VP8X`. The ICC profile stays since it changes how the image
+/// displays. `None` if the RIFF structure doesn't parse.
+fn strip_metadata(webp: &[u8]) -> Option<Vec<u8>> {
+    if webp.get(..4)? != b"RIFF" || webp.get(8..12)? != b"WEBP" {
+        return None;
+    }
 
-    // Create output path
-    let stem = input_path.file_stem().unwrap().to_string_lossy();
-    let initial_output = output_dir.join(format!("{}_compressed.webp", stem));
-    let output_path = deduplicate_path(&initial_output);
+    let mut output = b"RIFF\0\0\0\0WEBP".to_vec();
+    let mut offset = 12;
+    while offset < webp.len() {
+        let header = webp.get(offset..offset + 8)?;
+        let size = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        let padded = size + (size & 1);
+        let chunk = webp.get(offset..offset + 8 + size)?;
+        offset += 8 + padded;
 
-    fs::write(&output_path, &*encoded).map_err(|e| format!("Failed to write output: {}", e))?;
+        match &header[..4] {
+            b"EXIF" | b"XMP " => continue,
+            b"VP8X" => {
+                let mut chunk = chunk.to_vec();
+                // EXIF and XMP flags
+                *chunk.get_mut(8)? &= !(0x08 | 0x04);
+                output.extend_from_slice(&chunk);
+            }
+            _ => output.extend_from_slice(chunk),
+        }
+        if padded > size {
+            output.push(0);
+        }
+    }
+
+    let riff_size = u32::try_from(output.len() - 8).ok()?;
+    output[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(output)
+}
 
-    let compressed_size = encoded.len() as u64;
-    let reduction_percent = if original_size > 0 && compressed_size <= original_size {
-        100.0 * (original_size - compressed_size) as f32 / original_size as f32
-    } else if original_size > 0 && compressed_size > original_size {
-        // Compression resulted in larger file, show negative reduction
-        -100.0 * (compressed_size - original_size) as f32 / original_size as f32
+/// Whether every image in a WebP file, every frame for animations, is
+/// lossless (`VP8L`) rather than lossy (`VP8 `).
+pub fn is_lossless_webp(webp: &[u8]) -> bool {
+    let mut bitstreams = Vec::new();
+    collect_bitstreams(webp.get(12..).unwrap_or_default(), &mut bitstreams);
+    !bitstreams.is_empty() && bitstreams.iter().all(|fourcc| fourcc == b"VP8L")
+}
+
+/// Names of the image chunks in `chunks`, looking inside animation frames.
+fn collect_bitstreams(mut chunks: &[u8], found: &mut Vec<[u8; 4]>) {
+    while chunks.len() >= 8 {
+        let fourcc: [u8; 4] = chunks[..4].try_into().unwrap();
+        let size = u32::from_le_bytes(chunks[4..8].try_into().unwrap()) as usize;
+        let payload = chunks.get(8..8 + size).unwrap_or(&chunks[8..]);
+        match &fourcc {
+            b"VP8 " | b"VP8L" => found.push(fourcc),
+            // The frame's position, size and timing come before its chunks
+            b"ANMF" => collect_bitstreams(payload.get(16..).unwrap_or_default(), found),
+            _ => {}
+        }
+        chunks = chunks.get(8 + size + (size & 1)..).unwrap_or_default();
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn webp_compression(app: AppHandle, lossless: bool, quality: f32) -> Result<BatchReport> {
+    log_line!("WebP compression function called.");
+    start_app_batch(&app);
+
+    let method = if lossless {
+        CompressionMethod::WebpLossless
     } else {
-        0.0
+        CompressionMethod::WebpLossy
+    };
+    let settings = AppSettings {
+        compression_quality: quality,
+        method,
+        ..load_settings().unwrap_or_default()
     };
 
-    println!(
-        "\n Compressed {}: original size = {}, compressed size = {}, reduction = {:.2}%",
-        input_path.display(),
-        original_size,
-        compressed_size,
-        reduction_percent
-    );
-
-    Ok(CompressionResult {
-        original_path: input_path.display().to_string(),
-        compressed_path: output_path.display().to_string(),
-        original_size,
-        compressed_size,
-        reduction_percent,
-        original_base64: encode_file(&input_path.to_string_lossy())?,
-        compressed_base64: encode_file(&output_path.to_string_lossy())?,
-    })
+    run_app_job(&app, settings)
+}
+
+pub fn compress_to_webp(
+    input_path: &Path,
+    output_dir: &Path,
+    options: WebpOptions,
+) -> Result<CompressionResult, CompressionFailure> {
+    compress_file(
+        &WebpCompressor { options },
+        input_path,
+        output_dir,
+        &NamingPolicy::default(),
+    )
 }
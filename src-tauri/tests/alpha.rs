@@ -0,0 +1,129 @@
+//! Transparent inputs under a method that can't keep alpha are flattened,
+//! kept as they are or rerouted, as the settings say.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::{compress_one, scratch_dir, write_fixture};
+use compressor_tauri_lib::alpha::AlphaAction;
+use compressor_tauri_lib::job::CompressionJob;
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+use image::{ImageFormat, RgbaImage};
+
+/// Left half green, fully transparent unless `opaque`, right half opaque
+/// blue.
+fn fixture_image(opaque: bool) -> RgbaImage {
+    RgbaImage::from_fn(64, 32, |x, _| {
+        if x < 32 {
+            image::Rgba([0, 255, 0, if opaque { 255 } else { 0 }])
+        } else {
+            image::Rgba([0, 0, 255, 255])
+        }
+    })
+}
+
+fn write_transparent(dir: &Path) -> PathBuf {
+    write_fixture(dir, "transparent.png", fixture_image(false))
+}
+
+fn write_opaque(dir: &Path) -> PathBuf {
+    write_fixture(dir, "opaque.png", fixture_image(true))
+}
+
+fn lossy_settings(action: AlphaAction) -> AppSettings {
+    let mut settings = AppSettings {
+        method: CompressionMethod::Lossy,
+        compression_quality: 95.0,
+        ..AppSettings::default()
+    };
+    settings.alpha.action = action;
+    settings
+}
+
+#[test]
+fn jpeg_output_flattens_onto_the_background() {
+    let mut settings = lossy_settings(AlphaAction::Flatten);
+    settings.alpha.background = [255, 0, 0];
+    let compressed = compress_one("alpha-flatten", settings, write_transparent);
+
+    assert_eq!(compressed.result.alpha_action, Some(AlphaAction::Flatten));
+    assert!(compressed.result.compressed_path.ends_with(".jpg"));
+    let output = image::load_from_memory(&compressed.output)
+        .unwrap()
+        .to_rgb8();
+    let [r, g, b] = output.get_pixel(8, 16).0;
+    assert!(r > 230 && g < 30 && b < 30, "{:?}", [r, g, b]);
+}
+
+#[test]
+fn jpeg_output_can_keep_the_original() {
+    let compressed = compress_one(
+        "alpha-keep",
+        lossy_settings(AlphaAction::KeepOriginal),
+        write_transparent,
+    );
+
+    assert_eq!(
+        compressed.result.alpha_action,
+        Some(AlphaAction::KeepOriginal)
+    );
+    assert!(compressed.result.compressed_path.ends_with(".png"));
+    assert_eq!(compressed.output, compressed.input);
+}
+
+#[test]
+fn jpeg_output_can_reroute_to_a_method_with_alpha() {
+    let compressed = compress_one(
+        "alpha-reroute",
+        lossy_settings(AlphaAction::Reroute),
+        write_transparent,
+    );
+
+    assert_eq!(compressed.result.alpha_action, Some(AlphaAction::Reroute));
+    assert_eq!(
+        image::guess_format(&compressed.output).unwrap(),
+        ImageFormat::WebP
+    );
+    let output = image::load_from_memory(&compressed.output)
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(output.get_pixel(8, 16)[3], 0);
+}
+
+#[test]
+fn inputs_the_reroute_method_declines_are_flattened() {
+    // The GIF optimiser only takes GIFs and has no fallback
+    let mut settings = lossy_settings(AlphaAction::Reroute);
+    settings.alpha.reroute_method = CompressionMethod::Gif;
+    let compressed = compress_one("alpha-reroute-declined", settings, write_transparent);
+
+    assert_eq!(compressed.result.alpha_action, Some(AlphaAction::Flatten));
+    assert!(compressed.result.compressed_path.ends_with(".jpg"));
+}
+
+#[test]
+fn opaque_inputs_need_no_alpha_action() {
+    for action in [
+        AlphaAction::Flatten,
+        AlphaAction::KeepOriginal,
+        AlphaAction::Reroute,
+    ] {
+        let compressed = compress_one("alpha-opaque", lossy_settings(action), write_opaque);
+        assert_eq!(compressed.result.alpha_action, None);
+        assert!(compressed.result.compressed_path.ends_with(".jpg"));
+    }
+}
+
+#[test]
+fn reroute_method_has_to_keep_alpha() {
+    let dir = scratch_dir("alpha-bad-reroute");
+    let mut settings = lossy_settings(AlphaAction::Reroute);
+    settings.alpha.reroute_method = CompressionMethod::Lossy;
+
+    let job = CompressionJob::new(vec![write_transparent(&dir)], dir.join("out"), settings);
+    assert!(job.run().is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,272 @@
+//! Animated inputs keep every frame, their timing and their loop count.
+
+mod common;
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use common::scratch_dir;
+use compressor_tauri_lib::job::CompressionJob;
+use compressor_tauri_lib::lossless_compressor::ChunkStripping;
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Delay, Frame, Rgba, RgbaImage};
+
+const WIDTH: u32 = 40;
+const HEIGHT: u32 = 30;
+const DELAYS_MS: [u32; 4] = [100, 250, 100, 500];
+
+/// A square moving over a striped background. The last frame punches a
+/// transparent hole, which can't be drawn over the frame before it.
+fn fixture_frames() -> Vec<RgbaImage> {
+    (0..DELAYS_MS.len() as u32)
+        .map(|i| {
+            RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+                let in_square = (i * 8..i * 8 + 10).contains(&x) && (5..15).contains(&y);
+                let in_hole = i == 3 && x < 6 && y < 6;
+                if in_hole {
+                    Rgba([0, 0, 0, 0])
+                } else if in_square {
+                    Rgba([220, 40, 40, 255])
+                } else {
+                    Rgba([(y / 5 * 40) as u8, 120, 200, 255])
+                }
+            })
+        })
+        .collect()
+}
+
+fn write_gif_fixture(dir: &Path) -> PathBuf {
+    let path = dir.join("animated.gif");
+    let file = fs::File::create(&path).unwrap();
+    let mut encoder = GifEncoder::new(file);
+    encoder.set_repeat(Repeat::Finite(2)).unwrap();
+    let frames = fixture_frames()
+        .into_iter()
+        .zip(DELAYS_MS)
+        .map(|(image, ms)| Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(ms, 1)));
+    encoder.encode_frames(frames).unwrap();
+    path
+}
+
+/// The same animation as an APNG that plays three times.
+fn write_apng_fixture(dir: &Path) -> PathBuf {
+    let path = dir.join("animated.png");
+    let file = fs::File::create(&path).unwrap();
+    let mut encoder = png::Encoder::new(file, WIDTH, HEIGHT);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(DELAYS_MS.len() as u32, 3).unwrap();
+
+    let mut writer = encoder.write_header().unwrap();
+    for (image, ms) in fixture_frames().into_iter().zip(DELAYS_MS) {
+        writer.set_frame_delay(ms as u16, 1000).unwrap();
+        writer.write_image_data(image.as_raw()).unwrap();
+    }
+    writer.finish().unwrap();
+    path
+}
+
+fn decode_frames(bytes: &[u8]) -> Vec<Frame> {
+    GifDecoder::new(Cursor::new(bytes))
+        .unwrap()
+        .into_frames()
+        .collect_frames()
+        .unwrap()
+}
+
+fn repeat(bytes: &[u8]) -> gif::Repeat {
+    let mut decoder = gif::DecodeOptions::new().read_info(bytes).unwrap();
+    decoder.next_frame_info().unwrap();
+    decoder.repeat()
+}
+
+fn compress_gif(name: &str, settings: AppSettings) -> (Vec<u8>, u32) {
+    let dir = scratch_dir(name);
+    let input = write_gif_fixture(&dir);
+    let settings = AppSettings {
+        method: CompressionMethod::Gif,
+        ..settings
+    };
+
+    let report = CompressionJob::new(vec![input], dir.join("out"), settings)
+        .run()
+        .unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    let result = &report.completed[0];
+    let bytes = fs::read(&result.compressed_path).unwrap();
+
+    fs::remove_dir_all(&dir).unwrap();
+    (bytes, result.frame_count)
+}
+
+#[test]
+fn gif_keeps_frames_timing_and_loop_count() {
+    let (bytes, frame_count) = compress_gif("gif-lossless", AppSettings::default());
+
+    assert_eq!(frame_count, DELAYS_MS.len() as u32);
+    assert_eq!(repeat(&bytes), gif::Repeat::Finite(2));
+
+    let frames = decode_frames(&bytes);
+    assert_eq!(frames.len(), DELAYS_MS.len());
+    for ((frame, expected), ms) in frames.iter().zip(fixture_frames()).zip(DELAYS_MS) {
+        assert_eq!(frame.delay().numer_denom_ms(), (ms, 1));
+        assert_eq!(frame.buffer(), &expected);
+    }
+}
+
+#[test]
+fn lossy_gif_stays_close_to_the_original() {
+    let mut settings = AppSettings::default();
+    settings.gif.lossy = 60;
+    settings.gif.colors = 16;
+    let (bytes, frame_count) = compress_gif("gif-lossy", settings);
+
+    assert_eq!(frame_count, DELAYS_MS.len() as u32);
+
+    let frames = decode_frames(&bytes);
+    assert_eq!(frames.len(), DELAYS_MS.len());
+    for (frame, expected) in frames.iter().zip(fixture_frames()) {
+        for (got, want) in frame.buffer().pixels().zip(expected.pixels()) {
+            assert_eq!(got[3], want[3], "transparency must survive");
+            let distance: i32 = (0..3).map(|c| (got[c] as i32 - want[c] as i32).abs()).sum();
+            assert!(distance <= 120, "{:?} vs {:?}", got, want);
+        }
+    }
+}
+
+fn decode_apng_frames(bytes: &[u8]) -> Vec<Frame> {
+    PngDecoder::new(Cursor::new(bytes))
+        .unwrap()
+        .apng()
+        .unwrap()
+        .into_frames()
+        .collect_frames()
+        .unwrap()
+}
+
+/// Checks an APNG that should hold the fixture, playing three times.
+fn assert_apng_matches_fixture(bytes: &[u8]) {
+    let reader = png::Decoder::new(bytes).read_info().unwrap();
+    let control = reader.info().animation_control.unwrap();
+    assert_eq!(control.num_frames, DELAYS_MS.len() as u32);
+    assert_eq!(control.num_plays, 3);
+
+    let frames = decode_apng_frames(bytes);
+    assert_eq!(frames.len(), DELAYS_MS.len());
+    for ((frame, expected), ms) in frames.iter().zip(fixture_frames()).zip(DELAYS_MS) {
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        assert_eq!(numer / denom, ms);
+        assert_eq!(frame.buffer(), &expected);
+    }
+}
+
+fn compress_to_png(name: &str, input: fn(&Path) -> PathBuf, settings: AppSettings) -> Vec<u8> {
+    let dir = scratch_dir(name);
+    let input = input(&dir);
+
+    let report = CompressionJob::new(vec![input], dir.join("out"), settings)
+        .run()
+        .unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    let result = &report.completed[0];
+    assert_eq!(result.frame_count, DELAYS_MS.len() as u32);
+    assert!(result.compressed_path.ends_with(".png"));
+    let bytes = fs::read(&result.compressed_path).unwrap();
+
+    fs::remove_dir_all(&dir).unwrap();
+    bytes
+}
+
+#[test]
+fn lossless_keeps_apng_frames_under_every_strip_mode() {
+    for strip in [
+        ChunkStripping::Safe,
+        ChunkStripping::All,
+        ChunkStripping::Keep(vec!["tEXt".to_string()]),
+        ChunkStripping::Strip(vec!["acTL".to_string(), "fdAT".to_string()]),
+    ] {
+        let mut settings = AppSettings {
+            method: CompressionMethod::Lossless,
+            ..AppSettings::default()
+        };
+        settings.png.strip = strip;
+        let bytes = compress_to_png("apng-lossless", write_apng_fixture, settings);
+        assert_apng_matches_fixture(&bytes);
+    }
+}
+
+#[test]
+fn png_methods_write_animated_gif_as_apng() {
+    for method in [CompressionMethod::Lossless, CompressionMethod::QuantizedPng] {
+        let settings = AppSettings {
+            method,
+            ..AppSettings::default()
+        };
+        let bytes = compress_to_png(
+            &format!("gif-to-apng-{}", method.as_str()),
+            write_gif_fixture,
+            settings,
+        );
+        assert_apng_matches_fixture(&bytes);
+    }
+}
+
+#[test]
+fn webp_methods_write_animated_webp() {
+    for (method, input) in [
+        (
+            CompressionMethod::WebpLossy,
+            write_gif_fixture as fn(&Path) -> PathBuf,
+        ),
+        (CompressionMethod::WebpLossless, write_gif_fixture),
+        (CompressionMethod::WebpLossy, write_apng_fixture),
+        (CompressionMethod::WebpLossless, write_apng_fixture),
+    ] {
+        let dir = scratch_dir(&format!("animated-{}", method.as_str()));
+        let input = input(&dir);
+        let settings = AppSettings {
+            method,
+            ..AppSettings::default()
+        };
+
+        let report = CompressionJob::new(vec![input], dir.join("out"), settings)
+            .run()
+            .unwrap();
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        let result = &report.completed[0];
+        assert_eq!(result.frame_count, DELAYS_MS.len() as u32);
+
+        let bytes = fs::read(&result.compressed_path).unwrap();
+        let decoder = image_webp::WebPDecoder::new(Cursor::new(&bytes)).unwrap();
+        assert!(decoder.is_animated());
+        assert_eq!(decoder.num_frames(), DELAYS_MS.len() as u32);
+        // GIF's "repeat twice" is three plays, like the APNG fixture
+        assert_eq!(
+            decoder.loop_count(),
+            image_webp::LoopCount::Times(3.try_into().unwrap())
+        );
+
+        let frames = WebPDecoder::new(Cursor::new(&bytes))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        for (frame, ms) in frames.iter().zip(DELAYS_MS) {
+            assert_eq!(frame.delay().numer_denom_ms(), (ms, 1), "{:?}", method);
+        }
+
+        // image-webp rounds when blending frames, so check pixels with libwebp
+        if method == CompressionMethod::WebpLossless {
+            let frames = webp::AnimDecoder::new(&bytes).decode().unwrap();
+            for (frame, expected) in frames.into_iter().zip(fixture_frames()) {
+                assert_eq!(frame.get_image(), expected.as_raw().as_slice());
+            }
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
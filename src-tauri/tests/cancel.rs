@@ -0,0 +1,102 @@
+//! Cancelling a batch reports every input that never started, and cuts
+//! short the oxipng runs already underway.
+
+mod common;
+
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use common::{scratch_dir, write_fixture};
+use compressor_tauri_lib::compressor::{Compressor, SourceImage};
+use compressor_tauri_lib::job::{BatchControl, CompressionJob, ProgressEvent};
+use compressor_tauri_lib::jpeg_optimizer::LosslessJpegOptions;
+use compressor_tauri_lib::lossless_compressor::{LosslessCompressor, PngOptions};
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+use compressor_tauri_lib::webp_compressor::WebpOptions;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageFormat, RgbImage};
+
+#[test]
+fn cancelled_inputs_still_count_as_processed() {
+    let dir = scratch_dir("cancel");
+    let inputs: Vec<_> = (0..3)
+        .map(|i| write_fixture(&dir, &format!("{}.png", i), RgbImage::new(8, 8)))
+        .collect();
+    let settings = AppSettings {
+        method: CompressionMethod::Lossy,
+        ..AppSettings::default()
+    };
+
+    let job = CompressionJob::new(inputs, dir.join("out"), settings);
+    job.control.cancel();
+    let events = Mutex::new(Vec::new());
+    let report = job
+        .run_with_progress(&|event| events.lock().unwrap().push(event))
+        .unwrap();
+
+    assert_eq!(report.cancelled.len(), 3);
+    let mut processed: Vec<usize> = events
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .filter_map(|event| match event {
+            ProgressEvent::FileCancelled {
+                processed, total, ..
+            } => {
+                assert_eq!(total, 3);
+                Some(processed)
+            }
+            _ => None,
+        })
+        .collect();
+    processed.sort();
+    assert_eq!(processed, [1, 2, 3]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cancelled_png_skips_the_zopfli_pass() {
+    let image = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+        image::Rgb([(x * 4) as u8, (y * 4) as u8, ((x * y) % 251) as u8])
+    }));
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    let source = SourceImage {
+        path: PathBuf::from("gradient.png"),
+        bytes,
+        image,
+        animation: None,
+        orientation: Orientation::NoTransforms,
+        transformed: false,
+    };
+    let compressor = |options| LosslessCompressor {
+        options,
+        quantize: None,
+        jpeg: LosslessJpegOptions::default(),
+        webp: WebpOptions::default(),
+    };
+
+    let control = BatchControl::default();
+    control.cancel();
+    let cancelled = compressor(PngOptions {
+        zopfli: true,
+        zopfli_iterations: 255,
+        ..PngOptions::default()
+    })
+    .encode_cancellable(&source, &control)
+    .unwrap();
+    // What libdeflate alone gives with no time for trials
+    let libdeflate = compressor(PngOptions {
+        timeout_secs: Some(0),
+        ..PngOptions::default()
+    })
+    .encode(&source)
+    .unwrap();
+
+    assert_eq!(cancelled, libdeflate);
+}
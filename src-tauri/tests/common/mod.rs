@@ -0,0 +1,75 @@
+//! Helpers shared by the integration tests.
+
+// Each test binary compiles this module and only uses part of it
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use compressor_tauri_lib::job::CompressionJob;
+use compressor_tauri_lib::utility::{AppSettings, CompressionResult};
+use image::{DynamicImage, RgbImage};
+
+/// An empty directory under the system temp dir, unique to this test binary.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("stretta-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// Gradients with noise on top: far more than 256 colours, and enough detail
+/// to give the encoders something to do.
+pub fn noise_image(width: u32, height: u32) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        let noise = (x * 7919 + y * 104729) % 61;
+        image::Rgb([
+            (x * 2 + noise) as u8,
+            (y * 3 + noise) as u8,
+            (noise * 4) as u8,
+        ])
+    })
+}
+
+/// Saves `image` as `dir/name`, in the format the name's extension says.
+pub fn write_fixture(dir: &Path, name: &str, image: impl Into<DynamicImage>) -> PathBuf {
+    let path = dir.join(name);
+    image.into().save(&path).unwrap();
+    path
+}
+
+/// What a single compressed file came out as, read before its scratch dir
+/// goes.
+pub struct Compressed {
+    pub result: CompressionResult,
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+}
+
+/// Compresses the one file `write_input` puts in a fresh scratch dir,
+/// failing the test unless it comes out, and removes the dir afterwards.
+pub fn compress_one(
+    name: &str,
+    settings: AppSettings,
+    write_input: impl FnOnce(&Path) -> PathBuf,
+) -> Compressed {
+    let dir = scratch_dir(name);
+    let input = write_input(&dir);
+    let report = CompressionJob::new(vec![input.clone()], dir.join("out"), settings)
+        .run()
+        .unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    let result = report.completed.into_iter().next().unwrap();
+    let compressed = Compressed {
+        input: fs::read(input).unwrap(),
+        output: fs::read(&result.compressed_path).unwrap(),
+        result,
+    };
+
+    fs::remove_dir_all(&dir).unwrap();
+    compressed
+}
@@ -0,0 +1,119 @@
+//! `KeepFormat` writes each input back in the format its contents really are.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::scratch_dir;
+use compressor_tauri_lib::job::CompressionJob;
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+use image::codecs::jpeg::JpegEncoder;
+use image::{ImageFormat, RgbImage};
+
+fn fixture_image() -> RgbImage {
+    RgbImage::from_fn(64, 48, |x, y| {
+        let noise = (x * 7919 + y * 104729) % 31;
+        image::Rgb([(x * 3 + noise) as u8, (y * 5) as u8, (x + y + noise) as u8])
+    })
+}
+
+fn write_jpeg(path: &Path, quality: u8) {
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, quality)
+        .encode_image(&fixture_image())
+        .unwrap();
+    fs::write(path, jpeg).unwrap();
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn keep_format_follows_contents_and_keeps_extension_spelling() {
+    let dir = scratch_dir("keep-format");
+    let inputs = vec![dir.join("photo.jpeg"), dir.join("mislabelled.png")];
+    write_jpeg(&inputs[0], 95);
+    write_jpeg(&inputs[1], 95);
+    let settings = AppSettings {
+        method: CompressionMethod::KeepFormat,
+        ..AppSettings::default()
+    };
+
+    let report = CompressionJob::new(inputs, dir.join("out"), settings)
+        .run()
+        .unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+
+    let mut names: Vec<String> = report
+        .completed
+        .iter()
+        .map(|result| {
+            let bytes = fs::read(&result.compressed_path).unwrap();
+            assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Jpeg);
+            assert!(result.compressed_size < result.original_size);
+            file_name(&result.compressed_path)
+        })
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        ["mislabelled_compressed.jpg", "photo_compressed.jpeg"]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn keep_format_never_grows_a_file() {
+    let dir = scratch_dir("keep-format-grow");
+    let input = dir.join("small.jpg");
+    write_jpeg(&input, 20);
+    let compress = |input: &Path, output_dir: &str| -> PathBuf {
+        let settings = AppSettings {
+            method: CompressionMethod::KeepFormat,
+            ..AppSettings::default()
+        };
+        let report = CompressionJob::new(vec![input.to_path_buf()], dir.join(output_dir), settings)
+            .run()
+            .unwrap();
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        PathBuf::from(&report.completed[0].compressed_path)
+    };
+
+    // The first pass leaves nothing for a second one to gain
+    let optimised = compress(&input, "out-1");
+    let again = compress(&optimised, "out-2");
+    assert_eq!(fs::read(again).unwrap(), fs::read(optimised).unwrap());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn keep_format_leaves_jpeg_pixels_unchanged() {
+    let dir = scratch_dir("keep-format-pixels");
+    let input = dir.join("photo.jpg");
+    write_jpeg(&input, 90);
+    let settings = AppSettings {
+        method: CompressionMethod::KeepFormat,
+        ..AppSettings::default()
+    };
+
+    let report = CompressionJob::new(vec![input.clone()], dir.join("out"), settings)
+        .run()
+        .unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    let result = &report.completed[0];
+    assert!(result.compressed_size < result.original_size);
+    assert_eq!(
+        image::open(&result.compressed_path).unwrap().to_rgb8(),
+        image::open(&input).unwrap().to_rgb8()
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
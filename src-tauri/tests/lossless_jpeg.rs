@@ -0,0 +1,87 @@
+//! JPEGs under the lossless methods are rewritten without re-encoding their
+//! pixels.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::{compress_one, contains, noise_image};
+use compressor_tauri_lib::jpeg_optimizer::JpegMetadata;
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+use image::codecs::jpeg::JpegEncoder;
+
+const COMMENT: &[u8] = b"stretta test comment";
+
+/// A baseline JPEG with the standard Huffman tables and a COM marker.
+fn write_jpeg(dir: &Path) -> PathBuf {
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, 90)
+        .encode_image(&noise_image(96, 64))
+        .unwrap();
+
+    // COM segment straight after SOI
+    let mut comment = vec![0xFF, 0xFE];
+    comment.extend_from_slice(&(COMMENT.len() as u16 + 2).to_be_bytes());
+    comment.extend_from_slice(COMMENT);
+    jpeg.splice(2..2, comment);
+
+    let path = dir.join("photo.jpg");
+    fs::write(&path, jpeg).unwrap();
+    path
+}
+
+fn has_marker(jpeg: &[u8], marker: u8) -> bool {
+    jpeg.windows(2).any(|w| w == [0xFF, marker])
+}
+
+#[test]
+fn lossless_methods_keep_jpeg_pixels() {
+    for method in [CompressionMethod::Lossless, CompressionMethod::QuantizedPng] {
+        for progressive in [true, false] {
+            let mut settings = AppSettings {
+                method,
+                ..AppSettings::default()
+            };
+            settings.lossless_jpeg.progressive = progressive;
+            let compressed = compress_one("lossless-jpeg", settings, write_jpeg);
+            assert!(compressed.result.compressed_path.ends_with(".jpg"));
+            let (original, compressed) = (compressed.input, compressed.output);
+
+            assert!(compressed.len() < original.len(), "{:?}", method);
+            // SOF2 is progressive, SOF0 baseline
+            assert_eq!(has_marker(&compressed, 0xC2), progressive);
+            assert_eq!(has_marker(&compressed, 0xC0), !progressive);
+
+            let before = image::load_from_memory(&original).unwrap().to_rgb8();
+            let after = image::load_from_memory(&compressed).unwrap().to_rgb8();
+            assert_eq!(before, after, "{:?}", method);
+        }
+    }
+}
+
+#[test]
+fn lossless_jpeg_copies_only_the_chosen_metadata() {
+    for (metadata, keeps_comment) in [
+        (JpegMetadata::None, false),
+        (JpegMetadata::Icc, false),
+        (JpegMetadata::Comments, true),
+        (JpegMetadata::All, true),
+    ] {
+        let mut settings = AppSettings {
+            method: CompressionMethod::Lossless,
+            ..AppSettings::default()
+        };
+        settings.lossless_jpeg.metadata = metadata;
+        let compressed = compress_one("lossless-jpeg-metadata", settings, write_jpeg);
+        assert!(compressed.result.compressed_path.ends_with(".jpg"));
+        let compressed = compressed.output;
+
+        assert_eq!(
+            contains(&compressed, COMMENT),
+            keeps_comment,
+            "{:?}",
+            metadata
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! WebP inputs under the lossless method stay WebP, lose their EXIF and XMP,
+//! and keep their pixels.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::{contains, scratch_dir};
+use compressor_tauri_lib::job::CompressionJob;
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+use image::RgbaImage;
+
+const WIDTH: u32 = 48;
+const HEIGHT: u32 = 32;
+const EXIF: &[u8] = b"Exif\0\0stretta test exif";
+const XMP: &[u8] = b"<x:xmpmeta>stretta test xmp</x:xmpmeta>";
+
+fn fixture_image() -> RgbaImage {
+    RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        let alpha = if x < 8 { 0 } else { 255 };
+        image::Rgba([(x * 5) as u8, (y * 7) as u8, ((x * y) % 256) as u8, alpha])
+    })
+}
+
+fn chunk(fourcc: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut chunk = fourcc.to_vec();
+    chunk.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(payload);
+    if payload.len() % 2 == 1 {
+        chunk.push(0);
+    }
+    chunk
+}
+
+/// Wraps a simple-format WebP in a `VP8X` container carrying EXIF and XMP.
+fn with_metadata(simple: &[u8]) -> Vec<u8> {
+    // Alpha, EXIF and XMP flags, then the canvas size minus one
+    let mut vp8x = vec![0x10 | 0x08 | 0x04, 0, 0, 0];
+    vp8x.extend_from_slice(&(WIDTH - 1).to_le_bytes()[..3]);
+    vp8x.extend_from_slice(&(HEIGHT - 1).to_le_bytes()[..3]);
+
+    let mut body = b"WEBP".to_vec();
+    body.extend(chunk(b"VP8X", &vp8x));
+    body.extend_from_slice(&simple[12..]);
+    body.extend(chunk(b"EXIF", EXIF));
+    body.extend(chunk(b"XMP ", XMP));
+
+    let mut webp = b"RIFF".to_vec();
+    webp.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    webp.extend(body);
+    webp
+}
+
+fn write_fixture(dir: &Path, lossless: bool) -> PathBuf {
+    let image = fixture_image();
+    let encoder = webp::Encoder::from_rgba(&image, WIDTH, HEIGHT);
+    let simple = if lossless {
+        encoder.encode_lossless()
+    } else {
+        encoder.encode(70.0)
+    };
+
+    let path = dir.join(format!("fixture-{}.webp", lossless));
+    fs::write(&path, with_metadata(&simple)).unwrap();
+    path
+}
+
+#[test]
+fn lossless_method_keeps_webp_and_drops_metadata() {
+    for lossless in [true, false] {
+        let dir = scratch_dir(&format!("lossless-webp-{}", lossless));
+        let input = write_fixture(&dir, lossless);
+        let original = fs::read(&input).unwrap();
+        let settings = AppSettings {
+            method: CompressionMethod::Lossless,
+            ..AppSettings::default()
+        };
+
+        let report = CompressionJob::new(vec![input], dir.join("out"), settings)
+            .run()
+            .unwrap();
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        let result = &report.completed[0];
+        assert!(result.compressed_path.ends_with(".webp"));
+        let compressed = fs::read(&result.compressed_path).unwrap();
+
+        assert_eq!(
+            image::guess_format(&compressed).unwrap(),
+            image::ImageFormat::WebP
+        );
+        assert!(compressed.len() < original.len(), "lossless: {}", lossless);
+        assert!(!contains(&compressed, EXIF));
+        assert!(!contains(&compressed, XMP));
+
+        let before = image::load_from_memory(&original).unwrap().to_rgba8();
+        let after = image::load_from_memory(&compressed).unwrap().to_rgba8();
+        assert_eq!(before, after, "lossless: {}", lossless);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
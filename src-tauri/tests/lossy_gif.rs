@@ -0,0 +1,78 @@
+//! The lossy GIF encoder stays exact when its tolerance is below one colour
+//! step, however many times its dictionary fills up.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::compress_one;
+use compressor_tauri_lib::gif_compressor::GifOptions;
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+
+const SIZE: u16 = 512;
+
+/// 256 grey levels, far enough apart that no two are close at `lossy = 1`.
+fn palette() -> Vec<u8> {
+    (0..=255u8)
+        .flat_map(|level| [level, level, level])
+        .collect()
+}
+
+/// A single frame of noise over the whole palette, which fills the LZW
+/// dictionary and forces a clear code many times over.
+fn write_noise(dir: &Path) -> PathBuf {
+    let mut state = 0x2545_f491u32;
+    let indices: Vec<u8> = (0..usize::from(SIZE) * usize::from(SIZE))
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state >> 24) as u8
+        })
+        .collect();
+
+    let path = dir.join("noise.gif");
+    let file = fs::File::create(&path).unwrap();
+    let mut encoder = gif::Encoder::new(file, SIZE, SIZE, &palette()).unwrap();
+    let frame = gif::Frame {
+        width: SIZE,
+        height: SIZE,
+        buffer: indices.into(),
+        ..gif::Frame::default()
+    };
+    encoder.write_frame(&frame).unwrap();
+    path
+}
+
+fn decode_rgba(bytes: &[u8]) -> Vec<u8> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(bytes).unwrap();
+    let frame = decoder.read_next_frame().unwrap().unwrap();
+    assert_eq!((frame.width, frame.height), (SIZE, SIZE));
+    frame.buffer.to_vec()
+}
+
+#[test]
+fn noise_round_trips_exactly_at_the_lowest_tolerances() {
+    // 0 goes through the gif crate's encoder, 1 through the lossy one
+    for lossy in [0, 1] {
+        let settings = AppSettings {
+            method: CompressionMethod::Gif,
+            gif: GifOptions {
+                lossy,
+                ..GifOptions::default()
+            },
+            ..AppSettings::default()
+        };
+        let compressed = compress_one("lossy-gif-noise", settings, write_noise);
+
+        assert_eq!(
+            decode_rgba(&compressed.output),
+            decode_rgba(&compressed.input),
+            "lossy {}",
+            lossy
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! mozjpeg options change only what they're meant to.
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{compress_one, noise_image, write_fixture};
+use compressor_tauri_lib::compressor::{Compressor, SourceImage};
+use compressor_tauri_lib::lossy_compressor::{JpegOptions, LossyCompressor};
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+use image::metadata::Orientation;
+use image::{DynamicImage, RgbImage};
+
+/// Start of frame, baseline and progressive.
+const SOF: [u8; 2] = [0xC0, 0xC2];
+/// Quantisation tables.
+const DQT: u8 = 0xDB;
+/// Start of scan, where the entropy-coded data begins.
+const SOS: u8 = 0xDA;
+
+/// Header segments before the first scan with one of the given markers.
+fn segments(jpeg: &[u8], markers: &[u8]) -> Vec<Vec<u8>> {
+    let mut segments = Vec::new();
+    let mut offset = 2;
+    while jpeg[offset] == 0xFF && jpeg[offset + 1] != SOS {
+        let length = u16::from_be_bytes([jpeg[offset + 2], jpeg[offset + 3]]) as usize;
+        let segment = &jpeg[offset..offset + 2 + length];
+        if markers.contains(&jpeg[offset + 1]) {
+            segments.push(segment.to_vec());
+        }
+        offset += 2 + length;
+    }
+    segments
+}
+
+#[test]
+fn turning_trellis_off_keeps_the_rest_of_the_profile() {
+    // Noise gives trellis quantisation something to do
+    let compress = |name, trellis| {
+        let settings = AppSettings {
+            method: CompressionMethod::Lossy,
+            jpeg: JpegOptions {
+                trellis,
+                ..JpegOptions::default()
+            },
+            ..AppSettings::default()
+        };
+        compress_one(name, settings, |dir| {
+            write_fixture(dir, "noise.png", noise_image(96, 64))
+        })
+        .output
+    };
+    let with_trellis = compress("lossy-trellis-on", true);
+    let without_trellis = compress("lossy-trellis-off", false);
+
+    // Same tables, sampling and progressive mode, different coefficients
+    assert_ne!(with_trellis, without_trellis);
+    assert_eq!(
+        segments(&with_trellis, &[DQT]),
+        segments(&without_trellis, &[DQT])
+    );
+    assert_eq!(
+        segments(&with_trellis, &SOF),
+        segments(&without_trellis, &SOF)
+    );
+    assert_eq!(segments(&without_trellis, &[0xC2]).len(), 1);
+
+    let with_trellis = image::load_from_memory(&with_trellis).unwrap();
+    let without_trellis = image::load_from_memory(&without_trellis).unwrap();
+    assert_eq!(with_trellis.width(), without_trellis.width());
+    assert_eq!(with_trellis.height(), without_trellis.height());
+}
+
+#[test]
+fn empty_images_fail_instead_of_panicking() {
+    let source = SourceImage {
+        path: PathBuf::from("empty.png"),
+        bytes: Vec::new(),
+        image: DynamicImage::ImageRgb8(RgbImage::new(0, 0)),
+        animation: None,
+        orientation: Orientation::NoTransforms,
+        transformed: false,
+    };
+    let compressor = LossyCompressor {
+        options: JpegOptions::default(),
+    };
+    assert!(compressor.encode(&source).is_err());
+}
@@ -0,0 +1,188 @@
+//! Runs every `CompressionMethod` over every supported input format and checks
+//! the output against the table documented on `CompressionMethod`.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::scratch_dir;
+use compressor_tauri_lib::job::CompressionJob;
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+use image::{DynamicImage, ImageFormat, RgbImage};
+
+const WIDTH: u32 = 37;
+const HEIGHT: u32 = 23;
+
+const INPUTS: [(&str, ImageFormat); 5] = [
+    ("jpg", ImageFormat::Jpeg),
+    ("png", ImageFormat::Png),
+    ("gif", ImageFormat::Gif),
+    ("webp", ImageFormat::WebP),
+    ("bmp", ImageFormat::Bmp),
+];
+
+/// `None` where the method skips the input.
+fn expected_format(method: CompressionMethod, input: ImageFormat) -> Option<ImageFormat> {
+    let format = match (method, input) {
+        (CompressionMethod::Gif, ImageFormat::Gif) => ImageFormat::Gif,
+        (CompressionMethod::Gif, _) => return None,
+        (CompressionMethod::KeepFormat, ImageFormat::Bmp) => return None,
+        (CompressionMethod::KeepFormat, _) => input,
+        (CompressionMethod::Avif, _) => ImageFormat::Avif,
+        (CompressionMethod::JpegXl, _) => unreachable!("`image` doesn't know JPEG XL"),
+        (_, ImageFormat::Jpeg) | (CompressionMethod::Lossy, _) => ImageFormat::Jpeg,
+        (CompressionMethod::Lossless, ImageFormat::WebP) => ImageFormat::WebP,
+        (CompressionMethod::Lossless | CompressionMethod::QuantizedPng, _) => ImageFormat::Png,
+        (CompressionMethod::WebpLossy | CompressionMethod::WebpLossless, _) => ImageFormat::WebP,
+    };
+    Some(format)
+}
+
+fn write_fixtures(dir: &Path) -> Vec<PathBuf> {
+    let image = DynamicImage::ImageRgb8(RgbImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        image::Rgb([(x * 6) as u8, (y * 11) as u8, ((x + y) * 4) as u8])
+    }));
+
+    INPUTS
+        .iter()
+        .map(|(extension, format)| {
+            let path = dir.join(format!("fixture.{}", extension));
+            image.save_with_format(&path, *format).unwrap();
+            path
+        })
+        .collect()
+}
+
+/// `image` has no pure-Rust AVIF decoder, so AVIF dimensions come from the
+/// `ispe` property box instead.
+fn dimensions(bytes: &[u8], format: ImageFormat) -> (u32, u32) {
+    if format != ImageFormat::Avif {
+        let decoded = image::load_from_memory(bytes).unwrap();
+        return (decoded.width(), decoded.height());
+    }
+
+    let ispe = bytes.windows(4).position(|w| w == b"ispe").unwrap();
+    let read_u32 = |at: usize| u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+    // Box type, then version and flags, then width and height
+    (read_u32(ispe + 8), read_u32(ispe + 12))
+}
+
+/// `true` for lossless (VP8L) WebP, `false` for lossy (VP8).
+fn is_lossless_webp(bytes: &[u8]) -> bool {
+    &bytes[12..16] == b"VP8L"
+}
+
+#[test]
+fn every_method_handles_every_input_format() {
+    let fixtures_dir = scratch_dir("matrix-inputs");
+    let fixtures = write_fixtures(&fixtures_dir);
+
+    for method in [
+        CompressionMethod::Lossy,
+        CompressionMethod::Lossless,
+        CompressionMethod::QuantizedPng,
+        CompressionMethod::Gif,
+        CompressionMethod::WebpLossy,
+        CompressionMethod::WebpLossless,
+        CompressionMethod::Avif,
+        CompressionMethod::KeepFormat,
+    ] {
+        let output_dir = scratch_dir(&format!("matrix-{}", method.as_str()));
+        let settings = AppSettings {
+            method,
+            ..AppSettings::default()
+        };
+        let report = CompressionJob::new(fixtures.clone(), output_dir.clone(), settings)
+            .run()
+            .unwrap();
+
+        assert!(
+            report.failed.is_empty(),
+            "{:?}: {:?}",
+            method,
+            report.failed
+        );
+
+        let expected: Vec<(&str, ImageFormat)> = INPUTS
+            .iter()
+            .filter_map(|(extension, input)| {
+                expected_format(method, *input).map(|format| (*extension, format))
+            })
+            .collect();
+        assert_eq!(
+            report.skipped.len(),
+            INPUTS.len() - expected.len(),
+            "{:?}: {:?}",
+            method,
+            report.skipped
+        );
+        assert_eq!(report.completed.len(), expected.len(), "{:?}", method);
+
+        for ((extension, expected_format), result) in expected.iter().zip(&report.completed) {
+            let case = format!("{:?} x {}", method, extension);
+            let bytes = fs::read(&result.compressed_path).unwrap();
+
+            let format = image::guess_format(&bytes).unwrap();
+            assert_eq!(format, *expected_format, "{}", case);
+            assert_eq!(result.frame_count, 1, "{}", case);
+
+            assert_eq!(dimensions(&bytes, format), (WIDTH, HEIGHT), "{}", case);
+
+            if format == ImageFormat::WebP {
+                let lossless = matches!(
+                    method,
+                    CompressionMethod::WebpLossless
+                        | CompressionMethod::Lossless
+                        | CompressionMethod::KeepFormat
+                );
+                assert_eq!(is_lossless_webp(&bytes), lossless, "{}", case);
+            }
+        }
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    fs::remove_dir_all(&fixtures_dir).unwrap();
+}
+
+/// JPEG XL files start with either a bare codestream or the ISO BMFF container.
+#[cfg(feature = "jxl")]
+fn is_jpeg_xl(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0xFF, 0x0A]) || bytes.starts_with(b"\0\0\0\x0cJXL \r\n\x87\n")
+}
+
+#[cfg(feature = "jxl")]
+#[test]
+fn jpeg_xl_handles_every_input_format() {
+    let fixtures_dir = scratch_dir("jxl-inputs");
+    let fixtures = write_fixtures(&fixtures_dir);
+    let output_dir = scratch_dir("jxl-outputs");
+    let settings = AppSettings {
+        method: CompressionMethod::JpegXl,
+        ..AppSettings::default()
+    };
+
+    let report = CompressionJob::new(fixtures, output_dir.clone(), settings)
+        .run()
+        .unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert_eq!(report.completed.len(), INPUTS.len());
+
+    for ((extension, input_format), result) in INPUTS.iter().zip(&report.completed) {
+        let bytes = fs::read(&result.compressed_path).unwrap();
+        assert!(is_jpeg_xl(&bytes), "{}", extension);
+
+        // JPEG inputs are transcoded, keeping what's needed to rebuild them
+        let has_reconstruction = bytes.windows(4).any(|w| w == b"jbrd");
+        assert_eq!(
+            has_reconstruction,
+            *input_format == ImageFormat::Jpeg,
+            "{}",
+            extension
+        );
+    }
+
+    fs::remove_dir_all(&output_dir).unwrap();
+    fs::remove_dir_all(&fixtures_dir).unwrap();
+}
@@ -0,0 +1,142 @@
+//! Photos tagged with an EXIF orientation come out upright, or keep the tag
+//! when their pixels are passed through unchanged.
+
+mod common;
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use common::{Compressed, compress_one};
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod, CompressionResult};
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageReader, RgbImage};
+
+/// 64x32, red on the left and blue on the right.
+fn fixture_image() -> RgbImage {
+    RgbImage::from_fn(64, 32, |x, _| {
+        if x < 32 {
+            image::Rgb([255, 0, 0])
+        } else {
+            image::Rgb([0, 0, 255])
+        }
+    })
+}
+
+/// Little-endian TIFF with a single entry saying to rotate 90 degrees
+/// clockwise.
+fn orientation_tiff() -> Vec<u8> {
+    let mut tiff = b"II\x2a\0\x08\0\0\0\x01\0".to_vec();
+    tiff.extend_from_slice(&[0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00]);
+    tiff.extend_from_slice(&[6, 0, 0, 0, 0, 0, 0, 0]);
+    tiff
+}
+
+fn write_jpeg(dir: &Path) -> PathBuf {
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, 95)
+        .encode_image(&fixture_image())
+        .unwrap();
+
+    let mut exif = b"Exif\0\0".to_vec();
+    exif.extend(orientation_tiff());
+    let mut app1 = vec![0xFF, 0xE1];
+    app1.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+    app1.extend(exif);
+    jpeg.splice(2..2, app1);
+
+    let path = dir.join("portrait.jpg");
+    fs::write(&path, jpeg).unwrap();
+    path
+}
+
+fn write_png(dir: &Path) -> PathBuf {
+    let image = fixture_image();
+    let mut info = png::Info::with_size(image.width(), image.height());
+    info.color_type = png::ColorType::Rgb;
+    info.exif_metadata = Some(orientation_tiff().into());
+
+    let path = dir.join("portrait.png");
+    let file = fs::File::create(&path).unwrap();
+    let mut writer = png::Encoder::with_info(file, info)
+        .unwrap()
+        .write_header()
+        .unwrap();
+    writer.write_image_data(image.as_raw()).unwrap();
+    writer.finish().unwrap();
+    path
+}
+
+/// The stored pixels and the orientation tag, without applying it.
+fn decode(bytes: &[u8]) -> (DynamicImage, Orientation) {
+    let mut decoder = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .unwrap()
+        .into_decoder()
+        .unwrap();
+    let orientation = decoder.orientation().unwrap();
+    (DynamicImage::from_decoder(decoder).unwrap(), orientation)
+}
+
+fn assert_upright(result: &CompressionResult, image: DynamicImage, label: &str) {
+    assert_eq!((result.width, result.height), (32, 64));
+    let image = image.to_rgb8();
+    assert_eq!(image.dimensions(), (32, 64));
+    // The left edge is now the top
+    let [r, _, b] = image.get_pixel(16, 8).0;
+    assert!(r > 200 && b < 60, "{}: {:?}", label, [r, b]);
+    let [r, _, b] = image.get_pixel(16, 56).0;
+    assert!(r < 60 && b > 200, "{}: {:?}", label, [r, b]);
+}
+
+#[test]
+fn re_encoded_photos_come_out_upright() {
+    for method in [CompressionMethod::Lossy, CompressionMethod::WebpLossy] {
+        let Compressed { result, output, .. } = compress_one(
+            "orientation-upright",
+            AppSettings {
+                method,
+                ..AppSettings::default()
+            },
+            write_jpeg,
+        );
+        let (image, orientation) = decode(&output);
+
+        assert_eq!(orientation, Orientation::NoTransforms, "{:?}", method);
+        assert_upright(&result, image, &format!("{:?}", method));
+    }
+}
+
+#[test]
+fn lossless_png_comes_out_upright() {
+    let Compressed { result, output, .. } = compress_one(
+        "orientation-png",
+        AppSettings {
+            method: CompressionMethod::Lossless,
+            ..AppSettings::default()
+        },
+        write_png,
+    );
+    assert!(result.compressed_path.ends_with(".png"));
+
+    assert_upright(&result, image::load_from_memory(&output).unwrap(), "PNG");
+}
+
+#[test]
+fn lossless_jpeg_keeps_the_orientation_tag() {
+    let Compressed { result, output, .. } = compress_one(
+        "orientation-lossless",
+        AppSettings {
+            method: CompressionMethod::Lossless,
+            ..AppSettings::default()
+        },
+        write_jpeg,
+    );
+    assert!(result.compressed_path.ends_with(".jpg"));
+
+    // Default settings drop EXIF, but the orientation survives on its own
+    let (image, orientation) = decode(&output);
+    assert_eq!(orientation, Orientation::Rotate90);
+    assert_eq!((image.width(), image.height()), (64, 32));
+}
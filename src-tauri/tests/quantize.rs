@@ -0,0 +1,73 @@
+//! Quantised PNG writes palette images, and keeps full colour when the
+//! palette can't reach the quality floor.
+
+mod common;
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use common::{compress_one, noise_image, scratch_dir, write_fixture};
+use compressor_tauri_lib::job::CompressionJob;
+use compressor_tauri_lib::png_quantizer::QuantizeOptions;
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod};
+
+fn write_noise(dir: &Path) -> PathBuf {
+    write_fixture(dir, "noise.png", noise_image(64, 64))
+}
+
+fn quantize_settings(quantize: QuantizeOptions) -> AppSettings {
+    AppSettings {
+        method: CompressionMethod::QuantizedPng,
+        quantize,
+        ..AppSettings::default()
+    }
+}
+
+fn color_type(png: &[u8]) -> png::ColorType {
+    png::Decoder::new(Cursor::new(png))
+        .read_info()
+        .unwrap()
+        .info()
+        .color_type
+}
+
+#[test]
+fn reachable_quality_writes_a_palette_png() {
+    let quantize = QuantizeOptions {
+        min_quality: 0,
+        ..QuantizeOptions::default()
+    };
+    let compressed = compress_one("quantize-palette", quantize_settings(quantize), write_noise);
+    assert_eq!(color_type(&compressed.output), png::ColorType::Indexed);
+}
+
+#[test]
+fn unreachable_quality_keeps_full_colour() {
+    let quantize = QuantizeOptions {
+        min_quality: 100,
+        max_quality: 100,
+        ..QuantizeOptions::default()
+    };
+    let compressed = compress_one(
+        "quantize-fallback",
+        quantize_settings(quantize),
+        write_noise,
+    );
+    assert_eq!(color_type(&compressed.output), png::ColorType::Rgb);
+}
+
+#[test]
+fn inverted_quality_range_fails_the_batch() {
+    let dir = scratch_dir("quantize-invalid");
+    let settings = quantize_settings(QuantizeOptions {
+        min_quality: 90,
+        max_quality: 60,
+        ..QuantizeOptions::default()
+    });
+
+    let job = CompressionJob::new(vec![write_noise(&dir)], dir.join("out"), settings);
+    assert!(job.run().is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
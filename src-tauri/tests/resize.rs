@@ -0,0 +1,203 @@
+//! The resize stage runs before every backend and reports the final size.
+
+mod common;
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use common::{Compressed, compress_one, scratch_dir, write_fixture};
+use compressor_tauri_lib::job::CompressionJob;
+use compressor_tauri_lib::resize::{Fit, ResizeMode, ResizeOptions};
+use compressor_tauri_lib::utility::{AppSettings, CompressionMethod, CompressionResult};
+use image::codecs::gif::GifEncoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Delay, DynamicImage, Frame, ImageFormat, RgbaImage};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+fn fixture_image(shade: u8) -> RgbaImage {
+    RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        image::Rgba([x as u8, y as u8, shade, 255])
+    })
+}
+
+fn write_png(dir: &Path) -> PathBuf {
+    write_fixture(dir, "photo.png", fixture_image(0))
+}
+
+fn write_jpeg(dir: &Path) -> PathBuf {
+    let image = DynamicImage::ImageRgba8(fixture_image(0)).to_rgb8();
+    write_fixture(dir, "photo.jpg", image)
+}
+
+fn write_gif(dir: &Path) -> PathBuf {
+    let path = dir.join("animated.gif");
+    let mut encoder = GifEncoder::new(fs::File::create(&path).unwrap());
+    let frames = [0, 128, 255].map(|shade| {
+        Frame::from_parts(
+            fixture_image(shade),
+            0,
+            0,
+            Delay::from_numer_denom_ms(100, 1),
+        )
+    });
+    encoder.encode_frames(frames).unwrap();
+    path
+}
+
+fn settings(method: CompressionMethod, resize: ResizeOptions) -> AppSettings {
+    AppSettings {
+        method,
+        resize,
+        ..AppSettings::default()
+    }
+}
+
+fn resize(mode: ResizeMode) -> ResizeOptions {
+    ResizeOptions {
+        mode,
+        ..ResizeOptions::default()
+    }
+}
+
+fn assert_size(result: &CompressionResult, output: &[u8], size: (u32, u32)) {
+    assert_eq!((result.width, result.height), size);
+    let image = image::load_from_memory(output).unwrap();
+    assert_eq!((image.width(), image.height()), size);
+}
+
+#[test]
+fn every_method_resizes_before_encoding() {
+    let mode = ResizeMode::MaxSize {
+        max_width: Some(80),
+        max_height: None,
+    };
+    for method in [
+        CompressionMethod::Lossy,
+        CompressionMethod::Lossless,
+        CompressionMethod::QuantizedPng,
+        CompressionMethod::WebpLossy,
+        CompressionMethod::WebpLossless,
+        CompressionMethod::KeepFormat,
+    ] {
+        let Compressed { result, output, .. } =
+            compress_one("resize-methods", settings(method, resize(mode)), write_png);
+        assert_size(&result, &output, (80, 60));
+    }
+}
+
+#[test]
+fn resized_jpegs_stay_jpeg_under_the_lossless_methods() {
+    let mode = ResizeMode::Scale { percent: 50.0 };
+    for method in [CompressionMethod::Lossless, CompressionMethod::QuantizedPng] {
+        let Compressed { result, output, .. } =
+            compress_one("resize-jpeg", settings(method, resize(mode)), write_jpeg);
+        assert!(result.compressed_path.ends_with(".jpg"), "{:?}", method);
+        assert_eq!(image::guess_format(&output).unwrap(), ImageFormat::Jpeg);
+        assert_size(&result, &output, (80, 60));
+    }
+}
+
+#[test]
+fn exact_size_fits_fills_or_crops() {
+    for (fit, size) in [
+        (Fit::Fit, (40, 30)),
+        (Fit::Fill, (40, 40)),
+        (Fit::Crop, (40, 40)),
+    ] {
+        let mode = ResizeMode::Exact {
+            width: 40,
+            height: 40,
+            fit,
+        };
+        let Compressed { result, output, .. } = compress_one(
+            "resize-exact",
+            settings(CompressionMethod::Lossless, resize(mode)),
+            write_png,
+        );
+        assert_size(&result, &output, size);
+    }
+}
+
+#[test]
+fn upscaling_only_happens_when_allowed() {
+    let mode = ResizeMode::Scale { percent: 150.0 };
+    for (never_upscale, size) in [(true, (WIDTH, HEIGHT)), (false, (240, 180))] {
+        let options = ResizeOptions {
+            never_upscale,
+            ..resize(mode)
+        };
+        let Compressed { result, output, .. } = compress_one(
+            "resize-upscale",
+            settings(CompressionMethod::Lossless, options),
+            write_png,
+        );
+        assert_size(&result, &output, size);
+    }
+}
+
+#[test]
+fn animations_resize_every_frame() {
+    let mode = ResizeMode::Scale { percent: 50.0 };
+    let Compressed { result, output, .. } = compress_one(
+        "resize-animation",
+        settings(CompressionMethod::WebpLossless, resize(mode)),
+        write_gif,
+    );
+
+    assert_eq!((result.width, result.height), (80, 60));
+    assert_eq!(result.frame_count, 3);
+    let frames = WebPDecoder::new(Cursor::new(output))
+        .unwrap()
+        .into_frames()
+        .collect_frames()
+        .unwrap();
+    assert_eq!(frames.len(), 3);
+    for frame in frames {
+        assert_eq!(frame.buffer().dimensions(), (80, 60));
+    }
+}
+
+#[test]
+fn impossible_sizes_fail_the_batch() {
+    let dir = scratch_dir("resize-invalid");
+    let settings = settings(
+        CompressionMethod::Lossy,
+        resize(ResizeMode::Scale { percent: 0.0 }),
+    );
+
+    let job = CompressionJob::new(vec![write_png(&dir)], dir.join("out"), settings);
+    assert!(job.run().is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn oversized_targets_fail_the_batch() {
+    let dir = scratch_dir("resize-oversized");
+    let input = write_png(&dir);
+
+    for mode in [
+        ResizeMode::Scale { percent: 1e9 },
+        ResizeMode::Exact {
+            width: u32::MAX,
+            height: 100,
+            fit: Fit::Fill,
+        },
+        ResizeMode::MaxSize {
+            max_width: Some(70_000),
+            max_height: None,
+        },
+    ] {
+        let mut options = resize(mode);
+        options.never_upscale = false;
+        let settings = settings(CompressionMethod::Lossy, options);
+
+        let job = CompressionJob::new(vec![input.clone()], dir.join("out"), settings);
+        assert!(job.run().is_err(), "{:?}", mode);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
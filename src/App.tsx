@@ -1,5 +1,6 @@
 import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import "./App.css";
 import { MyDropzone } from "./components/dropzone";
 import ResultsPage from "./pages/ResultsPage";
@@ -7,7 +8,7 @@ import SettingsPage from "./pages/SettingsPage";
 import { Button } from "./components/ui/button";
 import { Card } from "./components/ui/card";
 import { Settings, FileImage, Download, Eye } from "lucide-react";
-import { handleExport } from "./lib/utils";
+import { errorMessage, handleExport } from "./lib/utils";
 
 export interface ImageMetadata {
   original_path: string;
@@ -15,10 +16,81 @@ export interface ImageMetadata {
   original_size: number;
   compressed_size: number;
   reduction_percent: number;
+  frame_count: number;
+  width: number;
+  height: number;
+  alpha_action: "flatten" | "keep_original" | "reroute" | null;
   original_base64: string;
   compressed_base64: string;
 }
 
+// Mirrors the Rust `ProgressEvent` enum emitted as "compression-progress"
+export type ProgressEvent =
+  | { kind: "batch_started"; total: number }
+  | { kind: "file_started"; file: string }
+  | {
+      kind: "file_done";
+      file: string;
+      original_size: number;
+      compressed_size: number;
+      elapsed_ms: number;
+      processed: number;
+      total: number;
+    }
+  | {
+      kind: "file_failed";
+      file: string;
+      stage: FailureStage;
+      error: string;
+      elapsed_ms: number;
+      processed: number;
+      total: number;
+    }
+  | { kind: "file_skipped"; file: string; processed: number; total: number }
+  | { kind: "file_cancelled"; file: string; processed: number; total: number }
+  | {
+      kind: "batch_finished";
+      completed: number;
+      skipped: number;
+      cancelled: number;
+      failed: number;
+      total: number;
+      elapsed_ms: number;
+    };
+
+export type FailureStage = "decode" | "encode" | "write";
+
+// Mirrors the Rust `CompressionFailure`
+export interface CompressionFailure {
+  file_name: string;
+  stage: FailureStage;
+  error: string;
+}
+
+// Mirrors the Rust `BatchReport` returned by `handle_images`
+export interface BatchReport {
+  completed: ImageMetadata[];
+  skipped: string[];
+  cancelled: string[];
+  failed: CompressionFailure[];
+}
+
+interface BatchProgress {
+  processed: number;
+  total: number;
+  startedAt: number;
+}
+
+const formatEta = (progress: BatchProgress) => {
+  if (progress.processed === 0) return "";
+  const elapsed = Date.now() - progress.startedAt;
+  const remaining =
+    (elapsed / progress.processed) * (progress.total - progress.processed);
+  const seconds = Math.round(remaining / 1000);
+  if (seconds < 60) return `about ${seconds}s left`;
+  return `about ${Math.floor(seconds / 60)}m ${seconds % 60}s left`;
+};
+
 function App() {
   const [images, setImages] = useState<File[] | undefined>();
   const [currentPage, setCurrentPage] = useState<
@@ -27,6 +99,11 @@ function App() {
   const [imagesDropped, setImagesDropped] = useState(false);
   const [compressing, setCompressing] = useState(false);
   const [results, setResults] = useState<ImageMetadata[]>([]);
+  const [failures, setFailures] = useState<CompressionFailure[]>([]);
+  const [skipped, setSkipped] = useState<string[]>([]);
+  const [cancelled, setCancelled] = useState<string[]>([]);
+  const [progress, setProgress] = useState<BatchProgress | null>(null);
+  const [paused, setPaused] = useState(false);
 
   async function handleImageDrop(files: File[]) {
     console.log("Files dropped:", files);
@@ -43,6 +120,23 @@ function App() {
     // send as base64 with filenames
     if (imagesDropped && _images && _images.length > 0) {
       setCompressing(true);
+      const unlisten = await listen<ProgressEvent>(
+        "compression-progress",
+        (event) => {
+          const payload = event.payload;
+          if (payload.kind === "batch_started") {
+            setProgress({
+              processed: 0,
+              total: payload.total,
+              startedAt: Date.now(),
+            });
+          } else if ("processed" in payload) {
+            setProgress((current) =>
+              current ? { ...current, processed: payload.processed } : current
+            );
+          }
+        }
+      );
       try {
         const imageDataArray = await Promise.all(
           _images.map((file) => {
@@ -69,11 +163,15 @@ function App() {
         console.log(
           `Starting compression of ${imageDataArray.length} images...`
         );
-        const resultData: ImageMetadata[] = await invoke("handle_images", {
+        const report: BatchReport = await invoke("handle_images", {
           images: imageDataArray,
         });
+        const resultData = report.completed;
         setResults(resultData);
-        console.log("Compression completed successfully", resultData);
+        setFailures(report.failed);
+        setSkipped(report.skipped);
+        setCancelled(report.cancelled);
+        console.log("Compression completed successfully", report);
         console.log("Compression results:", results);
 
         imagesDropped && setImagesDropped(false); // Reset images dropped state
@@ -83,9 +181,12 @@ function App() {
       } catch (error) {
         console.error("Image compression failed", error);
         // Show error to user but still navigate to results to show diagnostics
-        alert(`Compression error: ${error}. Check results page for details.`);
+        alert(`Compression error: ${errorMessage(error)}. Check results page for details.`);
         setCurrentPage("results");
       } finally {
+        unlisten();
+        setProgress(null);
+        setPaused(false);
         setCompressing(false);
       }
     } else {
@@ -97,6 +198,16 @@ function App() {
 
   
 
+  async function togglePause() {
+    await invoke(paused ? "resume_compression" : "pause_compression");
+    setPaused(!paused);
+  }
+
+  async function cancelCompression() {
+    await invoke("cancel_compression");
+    setPaused(false);
+  }
+
   function resetImages() {
     setImages(undefined);
     setImagesDropped(false);
@@ -214,11 +325,29 @@ function App() {
             {/* Loading Overlay */}
             {compressing && (
               <Card className="p-6">
-                <div className="flex items-center justify-center space-x-2">
-                  <div className="animate-spin rounded-full h-4 w-4 border-b-2 border-primary"></div>
-                  <p className="text-muted-foreground">
-                    Compressing images... please wait
-                  </p>
+                <div className="flex items-center justify-between gap-4">
+                  <div className="flex items-center space-x-2">
+                    {!paused && (
+                      <div className="animate-spin rounded-full h-4 w-4 border-b-2 border-primary"></div>
+                    )}
+                    <p className="text-muted-foreground">
+                      {paused
+                        ? "Compression paused"
+                        : progress && progress.total > 0
+                        ? `Compressing images... ${progress.processed} / ${
+                            progress.total
+                          } ${formatEta(progress)}`
+                        : "Compressing images... please wait"}
+                    </p>
+                  </div>
+                  <div className="flex gap-2">
+                    <Button variant="outline" onClick={togglePause}>
+                      {paused ? "Resume" : "Pause"}
+                    </Button>
+                    <Button variant="outline" onClick={cancelCompression}>
+                      Cancel
+                    </Button>
+                  </div>
                 </div>
               </Card>
             )}
@@ -239,6 +368,9 @@ function App() {
       ) : currentPage === "results" ? (
         <ResultsPage
           results={results}
+          failures={failures}
+          skipped={skipped}
+          cancelled={cancelled}
           onBackToMain={() => setCurrentPage("main")}
         />
       ) : (
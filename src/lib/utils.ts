@@ -8,6 +8,19 @@ export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs));
 }
 
+/** Shape of errors rejected by backend commands. */
+export interface StrettaError {
+  kind: "io" | "decode" | "encode" | "settings" | "validation";
+  message: string;
+}
+
+export function errorMessage(error: unknown): string {
+  if (typeof error === "object" && error !== null && "message" in error) {
+    return String((error as StrettaError).message);
+  }
+  return String(error);
+}
+
 export async function handleExport() {
   const dir = await open({ directory: true });
   if (dir) {
@@ -2,7 +2,7 @@ import React, { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { Button } from "../components/ui/button";
 import { Card } from "../components/ui/card";
-import { ImageMetadata } from "../App";
+import { CompressionFailure, ImageMetadata } from "../App";
 import { handleExport } from "../lib/utils";
 
 import {
@@ -11,6 +11,8 @@ import {
   Image as ImageIcon,
   FileImage,
   Download,
+  AlertTriangle,
+  Info,
 } from "lucide-react";
 import {
   ReactCompareSlider,
@@ -34,6 +36,15 @@ const imageName = (path: string) => {
   return path.split("/").pop() || "image";
 };
 
+const alphaActionLabels: Record<
+  NonNullable<ImageMetadata["alpha_action"]>,
+  string
+> = {
+  flatten: "transparency filled in",
+  keep_original: "kept for transparency",
+  reroute: "other method for transparency",
+};
+
 interface ImageComparisonProps {
   metadata: ImageMetadata;
   onImageSelect: (metadata: ImageMetadata) => void;
@@ -77,6 +88,11 @@ const ImageComparison: React.FC<ImageComparisonProps> = ({
                 {metadata.compressed_size
                   ? formatFileSize(metadata.compressed_size)
                   : "N/A"}
+                {` · ${metadata.width}×${metadata.height}`}
+                {metadata.frame_count > 1 &&
+                  ` · ${metadata.frame_count} frames`}
+                {metadata.alpha_action &&
+                  ` · ${alphaActionLabels[metadata.alpha_action]}`}
               </p>
             </div>
           </div>
@@ -114,13 +130,87 @@ const ImageComparison: React.FC<ImageComparisonProps> = ({
   );
 };
 
+interface FailedImagesProps {
+  failures: CompressionFailure[];
+}
+
+const FailedImages: React.FC<FailedImagesProps> = ({ failures }) => {
+  if (failures.length === 0) return null;
+
+  return (
+    <Card className="p-6 mb-6">
+      <div className="space-y-3">
+        <div className="flex items-center gap-2">
+          <AlertTriangle className="h-5 w-5 text-destructive" />
+          <h3 className="text-lg font-medium">
+            {failures.length} image{failures.length !== 1 ? "s" : ""} failed
+          </h3>
+        </div>
+        <ul className="space-y-2 text-sm">
+          {failures.map((failure, index) => (
+            <li key={`${failure.file_name}-${index}`}>
+              <span className="font-medium">{failure.file_name}</span>
+              <span className="text-muted-foreground">
+                {" "}
+                failed to {failure.stage}: {failure.error}
+              </span>
+            </li>
+          ))}
+        </ul>
+      </div>
+    </Card>
+  );
+};
+
+interface UnprocessedImagesProps {
+  skipped: string[];
+  cancelled: string[];
+}
+
+const plural = (count: number) => `${count} image${count !== 1 ? "s" : ""}`;
+
+const UnprocessedImages: React.FC<UnprocessedImagesProps> = ({
+  skipped,
+  cancelled,
+}) => {
+  if (skipped.length === 0 && cancelled.length === 0) return null;
+
+  return (
+    <Card className="p-6 mb-6">
+      <div className="flex items-start gap-2">
+        <Info className="h-5 w-5 text-muted-foreground" />
+        <div className="space-y-1 text-sm">
+          {skipped.length > 0 && (
+            <p>
+              {plural(skipped.length)} skipped, the selected method doesn't
+              handle {skipped.length !== 1 ? "their" : "its"} format
+            </p>
+          )}
+          {cancelled.length > 0 && (
+            <p>
+              {plural(cancelled.length)} not compressed because compression
+              was cancelled
+            </p>
+          )}
+        </div>
+      </div>
+    </Card>
+  );
+};
+
 interface ResultsPageProps {
   results: ImageMetadata[];
+  failures: CompressionFailure[];
+  skipped: string[];
+  cancelled: string[];
   onBackToMain: () => void;
 }
 
 const ResultsPage: React.FC<ResultsPageProps> = ({
   results,
+  failures,
+  skipped,
+  cancelled,
   onBackToMain,
 }: ResultsPageProps) => {
   const [imageMetadata, setImageMetadata] = useState<ImageMetadata[]>([]);
@@ -141,6 +231,16 @@ const ResultsPage: React.FC<ResultsPageProps> = ({
     }
   }, [results]);
 
+  useEffect(() => {
+    invoke("get_diagnostics")
+      .then((diagnosticsData) =>
+        setDiagnostics(JSON.stringify(diagnosticsData, null, 2))
+      )
+      .catch((diagError) =>
+        console.log("No diagnostics available:", diagError)
+      );
+  }, [results]);
+
   const loadImages = async () => {
     try {
       setLoading(true);
@@ -156,14 +256,6 @@ const ResultsPage: React.FC<ResultsPageProps> = ({
         if (metadata.length > 0) {
           setSelectedImage(metadata[0]);
         }
-
-        // Optionally load diagnostics if available
-        try {
-          const diagnosticsData: string = await invoke("get_diagnostics");
-          setDiagnostics(diagnosticsData);
-        } catch (diagError) {
-          console.log("No diagnostics available:", diagError);
-        }
       } catch (error) {
         console.log("No stored results available:", error);
         // This is normal if no compression has been done yet
@@ -211,6 +303,9 @@ const ResultsPage: React.FC<ResultsPageProps> = ({
             </div>
           </div>
 
+          <FailedImages failures={failures} />
+          <UnprocessedImages skipped={skipped} cancelled={cancelled} />
+
           <Card className="p-8 text-center">
             <div className="space-y-4">
               <ImageIcon className="h-12 w-12 text-muted-foreground mx-auto" />
@@ -256,6 +351,9 @@ const ResultsPage: React.FC<ResultsPageProps> = ({
           </div>
         </div>
 
+        <FailedImages failures={failures} />
+        <UnprocessedImages skipped={skipped} cancelled={cancelled} />
+
         {/* Diagnostics */}
         {diagnostics && (
           <Card className="p-6 mb-6">
@@ -8,30 +8,289 @@ import { Slider } from "@/components/ui/slider";
 import { toast } from "sonner";
 import { ThemeToggle } from "@/components/ThemeToggle";
 
+interface JpegOptions {
+  progressive: boolean;
+  chroma_subsampling: "4:4:4" | "4:2:2" | "4:2:0";
+  trellis: boolean;
+  optimize_scans: boolean;
+  smoothing: number;
+  quant_table:
+    | "mozjpeg"
+    | "annex_k"
+    | "flat"
+    | "ms_ssim"
+    | "robidoux"
+    | "psnr_hvs"
+    | "klein_silverstein_carney"
+    | "watson_taylor_borthwick"
+    | "ahumada_watson_peterson"
+    | "peterson_ahumada_watson";
+}
+
+interface WebpOptions {
+  method: number;
+  sns_strength: number;
+  filter_strength: number;
+  segments: number;
+  near_lossless: number;
+  alpha_quality: number;
+  exact: boolean;
+}
+
+type ChunkStripping =
+  | { mode: "none" | "safe" | "all" }
+  | { mode: "keep" | "strip"; chunks: string[] };
+
+interface LosslessJpegOptions {
+  progressive: boolean;
+  metadata: "none" | "comments" | "icc" | "all";
+}
+
+interface PngOptions {
+  level: number;
+  zopfli: boolean;
+  zopfli_iterations: number;
+  interlace: "off" | "on" | "keep";
+  strip: ChunkStripping;
+  bit_depth_reduction: boolean;
+  palette_reduction: boolean;
+  timeout_secs: number | null;
+}
+
+interface QuantizeOptions {
+  min_quality: number;
+  max_quality: number;
+  dithering: number;
+  speed: number;
+}
+
+interface GifOptions {
+  colors: number;
+  lossy: number;
+  crop_frames: boolean;
+  transparency: boolean;
+}
+
+interface AvifOptions {
+  speed: number;
+  alpha_quality: number;
+}
+
+interface JxlOptions {
+  distance: number | null;
+  effort: number;
+  lossless_jpeg: boolean;
+}
+
+type ResizeMode =
+  | { mode: "none" }
+  | { mode: "max_size"; max_width: number | null; max_height: number | null }
+  | { mode: "scale"; percent: number }
+  | { mode: "exact"; width: number; height: number; fit: "fit" | "fill" | "crop" };
+
+interface ResizeOptions {
+  mode: ResizeMode;
+  never_upscale: boolean;
+  filter: "nearest" | "triangle" | "catmull_rom" | "gaussian" | "lanczos3";
+}
+
+interface AlphaOptions {
+  action: "flatten" | "keep_original" | "reroute";
+  background: [number, number, number];
+  reroute_method: AppSettings["method"];
+}
+
 // Define the settings interface to match the Rust enum
 interface AppSettings {
   compression_quality: number;
-  method: "lossy" | "lossless" | "webp_lossy" | "webp_lossless";
+  method:
+    | "lossy"
+    | "lossless"
+    | "quantized_png"
+    | "gif"
+    | "webp_lossy"
+    | "webp_lossless"
+    | "avif"
+    | "jpeg_xl"
+    | "keep_format";
+  jpeg: JpegOptions;
+  webp: WebpOptions;
+  lossless_jpeg: LosslessJpegOptions;
+  png: PngOptions;
+  quantize: QuantizeOptions;
+  gif: GifOptions;
+  avif: AvifOptions;
+  jxl: JxlOptions;
+  alpha: AlphaOptions;
+  resize: ResizeOptions;
 }
 
 const defaultSettings: AppSettings = {
   compression_quality: 75,
   method: "webp_lossy",
+  jpeg: {
+    progressive: true,
+    chroma_subsampling: "4:2:0",
+    trellis: true,
+    optimize_scans: true,
+    smoothing: 0,
+    quant_table: "mozjpeg",
+  },
+  webp: {
+    method: 4,
+    sns_strength: 50,
+    filter_strength: 60,
+    segments: 4,
+    near_lossless: 100,
+    alpha_quality: 100,
+    exact: false,
+  },
+  lossless_jpeg: {
+    progressive: true,
+    metadata: "icc",
+  },
+  png: {
+    level: 6,
+    zopfli: false,
+    zopfli_iterations: 15,
+    interlace: "off",
+    strip: { mode: "safe" },
+    bit_depth_reduction: true,
+    palette_reduction: true,
+    timeout_secs: null,
+  },
+  quantize: {
+    min_quality: 65,
+    max_quality: 80,
+    dithering: 1,
+    speed: 4,
+  },
+  gif: {
+    colors: 256,
+    lossy: 0,
+    crop_frames: true,
+    transparency: true,
+  },
+  avif: {
+    speed: 6,
+    alpha_quality: 90,
+  },
+  jxl: {
+    distance: null,
+    effort: 7,
+    lossless_jpeg: true,
+  },
+  alpha: {
+    action: "flatten",
+    background: [255, 255, 255],
+    reroute_method: "webp_lossy",
+  },
+  resize: {
+    mode: { mode: "none" },
+    never_upscale: true,
+    filter: "lanczos3",
+  },
 };
 
+const defaultResizeModes: Record<ResizeMode["mode"], ResizeMode> = {
+  none: { mode: "none" },
+  max_size: { mode: "max_size", max_width: 1920, max_height: 1920 },
+  scale: { mode: "scale", percent: 50 },
+  exact: { mode: "exact", width: 1920, height: 1080, fit: "fit" },
+};
+
+const toHexColour = (rgb: [number, number, number]) =>
+  "#" + rgb.map((channel) => channel.toString(16).padStart(2, "0")).join("");
+
+const fromHexColour = (hex: string): [number, number, number] => [
+  parseInt(hex.slice(1, 3), 16),
+  parseInt(hex.slice(3, 5), 16),
+  parseInt(hex.slice(5, 7), 16),
+];
+
+const webpSliders: {
+  key: Exclude<keyof WebpOptions, "exact">;
+  label: string;
+  min: number;
+  max: number;
+}[] = [
+  { key: "method", label: "Effort", min: 0, max: 6 },
+  { key: "sns_strength", label: "Noise shaping", min: 0, max: 100 },
+  { key: "filter_strength", label: "Filter strength", min: 0, max: 100 },
+  { key: "segments", label: "Segments", min: 1, max: 4 },
+  { key: "near_lossless", label: "Near-lossless (100 = off)", min: 0, max: 100 },
+  { key: "alpha_quality", label: "Alpha quality", min: 0, max: 100 },
+];
+
+const selectClassName =
+  "w-full px-3 py-2 border border-input bg-background rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-ring focus:ring-offset-2";
+
 interface SettingsPageProps {
   onBackToMain: () => void;
 }
 
 const SettingsPage: React.FC<SettingsPageProps> = ({ onBackToMain }) => {
   const [settings, setSettings] = useState<AppSettings>(defaultSettings);
+  const [jxlAvailable, setJxlAvailable] = useState(false);
 
   useEffect(() => {
     invoke<AppSettings>("load_settings")
       .then((loadedSettings) => setSettings(loadedSettings))
       .catch(() => setSettings(defaultSettings));
+    // JPEG XL is an optional Cargo feature, so ask the build whether it has it
+    invoke<{ features: string[] }>("get_diagnostics")
+      .then((diagnostics) =>
+        setJxlAvailable(diagnostics.features.includes("jxl"))
+      )
+      .catch(() => setJxlAvailable(false));
   }, []);
 
+  const setJpeg = (jpeg: Partial<JpegOptions>) =>
+    setSettings({ ...settings, jpeg: { ...settings.jpeg, ...jpeg } });
+
+  const setWebp = (webp: Partial<WebpOptions>) =>
+    setSettings({ ...settings, webp: { ...settings.webp, ...webp } });
+
+  const setLosslessJpeg = (losslessJpeg: Partial<LosslessJpegOptions>) =>
+    setSettings({
+      ...settings,
+      lossless_jpeg: { ...settings.lossless_jpeg, ...losslessJpeg },
+    });
+
+  const setPng = (png: Partial<PngOptions>) =>
+    setSettings({ ...settings, png: { ...settings.png, ...png } });
+
+  const setStripMode = (mode: ChunkStripping["mode"]) =>
+    setPng({
+      strip:
+        mode === "keep" || mode === "strip"
+          ? { mode, chunks: "chunks" in settings.png.strip ? settings.png.strip.chunks : [] }
+          : { mode },
+    });
+
+  const setQuantize = (quantize: Partial<QuantizeOptions>) =>
+    setSettings({
+      ...settings,
+      quantize: { ...settings.quantize, ...quantize },
+    });
+
+  const setGif = (gif: Partial<GifOptions>) =>
+    setSettings({ ...settings, gif: { ...settings.gif, ...gif } });
+
+  const setAvif = (avif: Partial<AvifOptions>) =>
+    setSettings({ ...settings, avif: { ...settings.avif, ...avif } });
+
+  const setJxl = (jxl: Partial<JxlOptions>) =>
+    setSettings({ ...settings, jxl: { ...settings.jxl, ...jxl } });
+
+  const setAlpha = (alpha: Partial<AlphaOptions>) =>
+    setSettings({ ...settings, alpha: { ...settings.alpha, ...alpha } });
+
+  const setResize = (resize: Partial<ResizeOptions>) =>
+    setSettings({ ...settings, resize: { ...settings.resize, ...resize } });
+
+  const resizeMode = settings.resize.mode;
+
   const save = () => {
     invoke("save_settings", { settings }).then(() => {
       toast("Settings saved", {
@@ -112,15 +371,820 @@ const SettingsPage: React.FC<SettingsPageProps> = ({ onBackToMain }) => {
                         method: e.target.value as AppSettings["method"],
                       })
                     }
-                    className="w-full px-3 py-2 border border-input bg-background rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-ring focus:ring-offset-2"
+                    className={selectClassName}
                   >
                     <option value="lossy">Lossy (JPEG)</option>
                     <option value="lossless">Lossless (PNG)</option>
+                    <option value="quantized_png">
+                      Quantised PNG (256 colours)
+                    </option>
+                    <option value="gif">GIF (keeps animation)</option>
                     <option value="webp_lossy">WebP Lossy (recommended)</option>
                     <option value="webp_lossless">WebP Lossless</option>
+                    <option value="avif">AVIF (smallest, slowest)</option>
+                    <option value="jpeg_xl" disabled={!jxlAvailable}>
+                      {jxlAvailable ? "JPEG XL" : "JPEG XL (not in this build)"}
+                    </option>
+                    <option value="keep_format">Keep format</option>
                   </select>
                 </div>
 
+                <div className="space-y-4">
+                  <Label className="text-base font-medium">JPEG Options</Label>
+
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="jpeg-subsampling">Chroma subsampling</Label>
+                      <select
+                        id="jpeg-subsampling"
+                        value={settings.jpeg.chroma_subsampling}
+                        onChange={(e) =>
+                          setJpeg({
+                            chroma_subsampling: e.target
+                              .value as JpegOptions["chroma_subsampling"],
+                          })
+                        }
+                        className={selectClassName}
+                      >
+                        <option value="4:4:4">4:4:4 (sharpest colour)</option>
+                        <option value="4:2:2">4:2:2</option>
+                        <option value="4:2:0">4:2:0 (smallest)</option>
+                      </select>
+                    </div>
+
+                    <div className="space-y-2">
+                      <Label htmlFor="jpeg-quant-table">Quantisation table</Label>
+                      <select
+                        id="jpeg-quant-table"
+                        value={settings.jpeg.quant_table}
+                        onChange={(e) =>
+                          setJpeg({
+                            quant_table: e.target
+                              .value as JpegOptions["quant_table"],
+                          })
+                        }
+                        className={selectClassName}
+                      >
+                        <option value="mozjpeg">mozjpeg default</option>
+                        <option value="annex_k">JPEG Annex K</option>
+                        <option value="flat">Flat</option>
+                        <option value="ms_ssim">MS-SSIM tuned</option>
+                        <option value="robidoux">N. Robidoux</option>
+                        <option value="psnr_hvs">PSNR-HVS tuned</option>
+                        <option value="klein_silverstein_carney">
+                          Klein, Silverstein, Carney
+                        </option>
+                        <option value="watson_taylor_borthwick">
+                          Watson, Taylor, Borthwick
+                        </option>
+                        <option value="ahumada_watson_peterson">
+                          Ahumada, Watson, Peterson
+                        </option>
+                        <option value="peterson_ahumada_watson">
+                          Peterson, Ahumada, Watson
+                        </option>
+                      </select>
+                    </div>
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="jpeg-smoothing">
+                      Smoothing ({settings.jpeg.smoothing})
+                    </Label>
+                    <Slider
+                      id="jpeg-smoothing"
+                      max={100}
+                      min={0}
+                      step={1}
+                      value={[settings.jpeg.smoothing]}
+                      onValueChange={(value) => setJpeg({ smoothing: value[0] })}
+                    />
+                  </div>
+
+                  <div className="grid grid-cols-2 gap-2 text-sm">
+                    <label className="flex items-center gap-2">
+                      <input
+                        type="checkbox"
+                        checked={settings.jpeg.progressive}
+                        onChange={(e) =>
+                          setJpeg({ progressive: e.target.checked })
+                        }
+                      />
+                      Progressive
+                    </label>
+                    <label className="flex items-center gap-2">
+                      <input
+                        type="checkbox"
+                        checked={settings.jpeg.optimize_scans}
+                        disabled={!settings.jpeg.progressive}
+                        onChange={(e) =>
+                          setJpeg({ optimize_scans: e.target.checked })
+                        }
+                      />
+                      Optimise scans
+                    </label>
+                    <label className="flex items-center gap-2">
+                      <input
+                        type="checkbox"
+                        checked={settings.jpeg.trellis}
+                        onChange={(e) => setJpeg({ trellis: e.target.checked })}
+                      />
+                      Trellis quantisation
+                    </label>
+                  </div>
+                </div>
+
+                <div className="space-y-4">
+                  <Label className="text-base font-medium">Resize</Label>
+                  <p className="text-sm text-muted-foreground">
+                    Applied to every image before it's compressed, whatever the
+                    method.
+                  </p>
+
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="resize-mode">Mode</Label>
+                      <select
+                        id="resize-mode"
+                        value={resizeMode.mode}
+                        onChange={(e) =>
+                          setResize({
+                            mode: defaultResizeModes[
+                              e.target.value as ResizeMode["mode"]
+                            ],
+                          })
+                        }
+                        className={selectClassName}
+                      >
+                        <option value="none">Keep the original size</option>
+                        <option value="max_size">Maximum size</option>
+                        <option value="scale">Percentage</option>
+                        <option value="exact">Exact size</option>
+                      </select>
+                    </div>
+
+                    <div className="space-y-2">
+                      <Label htmlFor="resize-filter">Resampling filter</Label>
+                      <select
+                        id="resize-filter"
+                        value={settings.resize.filter}
+                        disabled={resizeMode.mode === "none"}
+                        onChange={(e) =>
+                          setResize({
+                            filter: e.target.value as ResizeOptions["filter"],
+                          })
+                        }
+                        className={selectClassName}
+                      >
+                        <option value="lanczos3">Lanczos3 (sharpest)</option>
+                        <option value="catmull_rom">Catmull-Rom</option>
+                        <option value="gaussian">Gaussian</option>
+                        <option value="triangle">Bilinear</option>
+                        <option value="nearest">Nearest neighbour</option>
+                      </select>
+                    </div>
+                  </div>
+
+                  {resizeMode.mode === "max_size" && (
+                    <div className="grid grid-cols-2 gap-4">
+                      <div className="space-y-2">
+                        <Label htmlFor="resize-max-width">Max width (px)</Label>
+                        <input
+                          id="resize-max-width"
+                          type="number"
+                          min={1}
+                          max={65535}
+                          placeholder="No limit"
+                          value={resizeMode.max_width ?? ""}
+                          onChange={(e) =>
+                            setResize({
+                              mode: {
+                                ...resizeMode,
+                                max_width: e.target.value
+                                  ? Number(e.target.value)
+                                  : null,
+                              },
+                            })
+                          }
+                          className={selectClassName}
+                        />
+                      </div>
+                      <div className="space-y-2">
+                        <Label htmlFor="resize-max-height">Max height (px)</Label>
+                        <input
+                          id="resize-max-height"
+                          type="number"
+                          min={1}
+                          max={65535}
+                          placeholder="No limit"
+                          value={resizeMode.max_height ?? ""}
+                          onChange={(e) =>
+                            setResize({
+                              mode: {
+                                ...resizeMode,
+                                max_height: e.target.value
+                                  ? Number(e.target.value)
+                                  : null,
+                              },
+                            })
+                          }
+                          className={selectClassName}
+                        />
+                      </div>
+                    </div>
+                  )}
+
+                  {resizeMode.mode === "scale" && (
+                    <div className="space-y-2">
+                      <Label htmlFor="resize-percent">
+                        Scale ({resizeMode.percent}%)
+                      </Label>
+                      <Slider
+                        id="resize-percent"
+                        min={5}
+                        max={200}
+                        step={5}
+                        value={[resizeMode.percent]}
+                        onValueChange={(value) =>
+                          setResize({
+                            mode: { ...resizeMode, percent: value[0] },
+                          })
+                        }
+                      />
+                    </div>
+                  )}
+
+                  {resizeMode.mode === "exact" && (
+                    <div className="grid grid-cols-3 gap-4">
+                      <div className="space-y-2">
+                        <Label htmlFor="resize-width">Width (px)</Label>
+                        <input
+                          id="resize-width"
+                          type="number"
+                          min={1}
+                          max={65535}
+                          value={resizeMode.width}
+                          onChange={(e) =>
+                            setResize({
+                              mode: { ...resizeMode, width: Number(e.target.value) },
+                            })
+                          }
+                          className={selectClassName}
+                        />
+                      </div>
+                      <div className="space-y-2">
+                        <Label htmlFor="resize-height">Height (px)</Label>
+                        <input
+                          id="resize-height"
+                          type="number"
+                          min={1}
+                          max={65535}
+                          value={resizeMode.height}
+                          onChange={(e) =>
+                            setResize({
+                              mode: { ...resizeMode, height: Number(e.target.value) },
+                            })
+                          }
+                          className={selectClassName}
+                        />
+                      </div>
+                      <div className="space-y-2">
+                        <Label htmlFor="resize-fit">Fit</Label>
+                        <select
+                          id="resize-fit"
+                          value={resizeMode.fit}
+                          onChange={(e) =>
+                            setResize({
+                              mode: {
+                                ...resizeMode,
+                                fit: e.target.value as "fit" | "fill" | "crop",
+                              },
+                            })
+                          }
+                          className={selectClassName}
+                        >
+                          <option value="fit">Fit inside</option>
+                          <option value="fill">Stretch</option>
+                          <option value="crop">Fill and crop</option>
+                        </select>
+                      </div>
+                    </div>
+                  )}
+
+                  {resizeMode.mode !== "none" && resizeMode.mode !== "max_size" && (
+                    <label className="flex items-center gap-2 text-sm">
+                      <input
+                        type="checkbox"
+                        checked={settings.resize.never_upscale}
+                        onChange={(e) =>
+                          setResize({ never_upscale: e.target.checked })
+                        }
+                      />
+                      Never upscale
+                    </label>
+                  )}
+                </div>
+
+                <div className="space-y-4">
+                  <Label className="text-base font-medium">Transparency</Label>
+                  <p className="text-sm text-muted-foreground">
+                    What to do with transparent images when the output is JPEG,
+                    which can't keep transparency.
+                  </p>
+
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="alpha-action">Transparent images</Label>
+                      <select
+                        id="alpha-action"
+                        value={settings.alpha.action}
+                        onChange={(e) =>
+                          setAlpha({
+                            action: e.target.value as AlphaOptions["action"],
+                          })
+                        }
+                        className={selectClassName}
+                      >
+                        <option value="flatten">Fill in the background</option>
+                        <option value="keep_original">Keep the original</option>
+                        <option value="reroute">Use another method</option>
+                      </select>
+                    </div>
+
+                    {settings.alpha.action === "flatten" && (
+                      <div className="space-y-2">
+                        <Label htmlFor="alpha-background">Background colour</Label>
+                        <input
+                          id="alpha-background"
+                          type="color"
+                          value={toHexColour(settings.alpha.background)}
+                          onChange={(e) =>
+                            setAlpha({ background: fromHexColour(e.target.value) })
+                          }
+                          className="h-9 w-full rounded-md border border-input bg-background"
+                        />
+                      </div>
+                    )}
+
+                    {settings.alpha.action === "reroute" && (
+                      <div className="space-y-2">
+                        <Label htmlFor="alpha-reroute">Method</Label>
+                        <select
+                          id="alpha-reroute"
+                          value={settings.alpha.reroute_method}
+                          onChange={(e) =>
+                            setAlpha({
+                              reroute_method: e.target
+                                .value as AlphaOptions["reroute_method"],
+                            })
+                          }
+                          className={selectClassName}
+                        >
+                          <option value="lossless">Lossless (PNG)</option>
+                          <option value="quantized_png">Quantised PNG</option>
+                          <option value="webp_lossy">WebP Lossy</option>
+                          <option value="webp_lossless">WebP Lossless</option>
+                          <option value="avif">AVIF</option>
+                        </select>
+                      </div>
+                    )}
+                  </div>
+                </div>
+
+                <div className="space-y-4">
+                  <Label className="text-base font-medium">
+                    Lossless JPEG Options
+                  </Label>
+                  <p className="text-sm text-muted-foreground">
+                    Used for JPEGs under the lossless methods. The pixels are
+                    never re-encoded.
+                  </p>
+
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="lossless-jpeg-metadata">Keep metadata</Label>
+                      <select
+                        id="lossless-jpeg-metadata"
+                        value={settings.lossless_jpeg.metadata}
+                        onChange={(e) =>
+                          setLosslessJpeg({
+                            metadata: e.target
+                              .value as LosslessJpegOptions["metadata"],
+                          })
+                        }
+                        className={selectClassName}
+                      >
+                        <option value="none">None</option>
+                        <option value="comments">Comments</option>
+                        <option value="icc">Colour profile</option>
+                        <option value="all">All (including EXIF)</option>
+                      </select>
+                    </div>
+
+                    <label className="flex items-center gap-2 text-sm">
+                      <input
+                        type="checkbox"
+                        checked={settings.lossless_jpeg.progressive}
+                        onChange={(e) =>
+                          setLosslessJpeg({ progressive: e.target.checked })
+                        }
+                      />
+                      Progressive
+                    </label>
+                  </div>
+                </div>
+
+                <div className="space-y-4">
+                  <Label className="text-base font-medium">WebP Options</Label>
+
+                  <div className="grid grid-cols-2 gap-4">
+                    {webpSliders.map(({ key, label, min, max }) => (
+                      <div key={key} className="space-y-2">
+                        <Label htmlFor={`webp-${key}`}>
+                          {label} ({settings.webp[key]})
+                        </Label>
+                        <Slider
+                          id={`webp-${key}`}
+                          min={min}
+                          max={max}
+                          step={1}
+                          value={[settings.webp[key]]}
+                          onValueChange={(value) => setWebp({ [key]: value[0] })}
+                        />
+                      </div>
+                    ))}
+                  </div>
+
+                  <label className="flex items-center gap-2 text-sm">
+                    <input
+                      type="checkbox"
+                      checked={settings.webp.exact}
+                      onChange={(e) => setWebp({ exact: e.target.checked })}
+                    />
+                    Keep colour under transparent pixels
+                  </label>
+                </div>
+
+                <div className="space-y-4">
+                  <Label className="text-base font-medium">PNG Options</Label>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="png-level">
+                      Optimisation level ({settings.png.level})
+                    </Label>
+                    <Slider
+                      id="png-level"
+                      min={0}
+                      max={6}
+                      step={1}
+                      value={[settings.png.level]}
+                      onValueChange={(value) => setPng({ level: value[0] })}
+                    />
+                  </div>
+
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="png-interlace">Interlacing</Label>
+                      <select
+                        id="png-interlace"
+                        value={settings.png.interlace}
+                        onChange={(e) =>
+                          setPng({
+                            interlace: e.target.value as PngOptions["interlace"],
+                          })
+                        }
+                        className={selectClassName}
+                      >
+                        <option value="off">Off</option>
+                        <option value="on">On (Adam7)</option>
+                        <option value="keep">Keep input's</option>
+                      </select>
+                    </div>
+
+                    <div className="space-y-2">
+                      <Label htmlFor="png-strip">Metadata chunks</Label>
+                      <select
+                        id="png-strip"
+                        value={settings.png.strip.mode}
+                        onChange={(e) =>
+                          setStripMode(e.target.value as ChunkStripping["mode"])
+                        }
+                        className={selectClassName}
+                      >
+                        <option value="none">Keep all</option>
+                        <option value="safe">Strip non-display chunks</option>
+                        <option value="all">Strip all</option>
+                        <option value="keep">Strip all except…</option>
+                        <option value="strip">Strip only…</option>
+                      </select>
+                    </div>
+                  </div>
+
+                  {"chunks" in settings.png.strip && (
+                    <div className="space-y-2">
+                      <Label htmlFor="png-chunks">
+                        Chunk names, comma separated (e.g. iCCP, tEXt)
+                      </Label>
+                      <input
+                        id="png-chunks"
+                        value={settings.png.strip.chunks.join(", ")}
+                        onChange={(e) =>
+                          setPng({
+                            strip: {
+                              mode: settings.png.strip.mode as "keep" | "strip",
+                              chunks: e.target.value
+                                .split(",")
+                                .map((name) => name.trim())
+                                .filter(Boolean),
+                            },
+                          })
+                        }
+                        className={selectClassName}
+                      />
+                    </div>
+                  )}
+
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="png-zopfli-iterations">
+                        Zopfli iterations
+                      </Label>
+                      <input
+                        id="png-zopfli-iterations"
+                        type="number"
+                        min={1}
+                        max={255}
+                        disabled={!settings.png.zopfli}
+                        value={settings.png.zopfli_iterations}
+                        onChange={(e) =>
+                          setPng({ zopfli_iterations: Number(e.target.value) })
+                        }
+                        className={selectClassName}
+                      />
+                    </div>
+
+                    <div className="space-y-2">
+                      <Label htmlFor="png-timeout">
+                        Per-file timeout (seconds, empty for none)
+                      </Label>
+                      <input
+                        id="png-timeout"
+                        type="number"
+                        min={1}
+                        value={settings.png.timeout_secs ?? ""}
+                        onChange={(e) =>
+                          setPng({
+                            timeout_secs: e.target.value
+                              ? Number(e.target.value)
+                              : null,
+                          })
+                        }
+                        className={selectClassName}
+                      />
+                    </div>
+                  </div>
+
+                  <div className="grid grid-cols-2 gap-2 text-sm">
+                    <label className="flex items-center gap-2">
+                      <input
+                        type="checkbox"
+                        checked={settings.png.zopfli}
+                        onChange={(e) => setPng({ zopfli: e.target.checked })}
+                      />
+                      Zopfli (slow, smaller)
+                    </label>
+                    <label className="flex items-center gap-2">
+                      <input
+                        type="checkbox"
+                        checked={settings.png.bit_depth_reduction}
+                        onChange={(e) =>
+                          setPng({ bit_depth_reduction: e.target.checked })
+                        }
+                      />
+                      Bit depth reduction
+                    </label>
+                    <label className="flex items-center gap-2">
+                      <input
+                        type="checkbox"
+                        checked={settings.png.palette_reduction}
+                        onChange={(e) =>
+                          setPng({ palette_reduction: e.target.checked })
+                        }
+                      />
+                      Palette reduction
+                    </label>
+                  </div>
+                </div>
+
+                <div className="space-y-4">
+                  <Label className="text-base font-medium">
+                    Quantised PNG Options
+                  </Label>
+
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="quantize-quality">
+                        Quality range ({settings.quantize.min_quality}–
+                        {settings.quantize.max_quality})
+                      </Label>
+                      <Slider
+                        id="quantize-quality"
+                        min={0}
+                        max={100}
+                        step={1}
+                        value={[
+                          settings.quantize.min_quality,
+                          settings.quantize.max_quality,
+                        ]}
+                        onValueChange={(value) =>
+                          setQuantize({
+                            min_quality: value[0],
+                            max_quality: value[1],
+                          })
+                        }
+                      />
+                    </div>
+
+                    <div className="space-y-2">
+                      <Label htmlFor="quantize-dithering">
+                        Dithering ({settings.quantize.dithering})
+                      </Label>
+                      <Slider
+                        id="quantize-dithering"
+                        min={0}
+                        max={1}
+                        step={0.1}
+                        value={[settings.quantize.dithering]}
+                        onValueChange={(value) =>
+                          setQuantize({ dithering: value[0] })
+                        }
+                      />
+                    </div>
+
+                    <div className="space-y-2">
+                      <Label htmlFor="quantize-speed">
+                        Speed ({settings.quantize.speed}, 1 = best palette)
+                      </Label>
+                      <Slider
+                        id="quantize-speed"
+                        min={1}
+                        max={10}
+                        step={1}
+                        value={[settings.quantize.speed]}
+                        onValueChange={(value) =>
+                          setQuantize({ speed: value[0] })
+                        }
+                      />
+                    </div>
+                  </div>
+                </div>
+
+                <div className="space-y-4">
+                  <Label className="text-base font-medium">GIF Options</Label>
+
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="gif-colors">
+                        Colours ({settings.gif.colors})
+                      </Label>
+                      <Slider
+                        id="gif-colors"
+                        min={2}
+                        max={256}
+                        step={1}
+                        value={[settings.gif.colors]}
+                        onValueChange={(value) => setGif({ colors: value[0] })}
+                      />
+                    </div>
+
+                    <div className="space-y-2">
+                      <Label htmlFor="gif-lossy">
+                        Lossy ({settings.gif.lossy}, 0 = off)
+                      </Label>
+                      <Slider
+                        id="gif-lossy"
+                        min={0}
+                        max={100}
+                        step={1}
+                        value={[settings.gif.lossy]}
+                        onValueChange={(value) => setGif({ lossy: value[0] })}
+                      />
+                    </div>
+                  </div>
+
+                  <div className="flex flex-wrap gap-4">
+                    <label className="flex items-center gap-2 text-sm">
+                      <input
+                        type="checkbox"
+                        checked={settings.gif.crop_frames}
+                        onChange={(e) =>
+                          setGif({ crop_frames: e.target.checked })
+                        }
+                      />
+                      Crop frames to what changed
+                    </label>
+                    <label className="flex items-center gap-2 text-sm">
+                      <input
+                        type="checkbox"
+                        checked={settings.gif.transparency}
+                        onChange={(e) =>
+                          setGif({ transparency: e.target.checked })
+                        }
+                      />
+                      Make unchanged pixels transparent
+                    </label>
+                  </div>
+                </div>
+
+                <div className="space-y-4">
+                  <Label className="text-base font-medium">AVIF Options</Label>
+
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="avif-speed">
+                        Speed ({settings.avif.speed}, 1 = smallest)
+                      </Label>
+                      <Slider
+                        id="avif-speed"
+                        min={1}
+                        max={10}
+                        step={1}
+                        value={[settings.avif.speed]}
+                        onValueChange={(value) => setAvif({ speed: value[0] })}
+                      />
+                    </div>
+
+                    <div className="space-y-2">
+                      <Label htmlFor="avif-alpha-quality">
+                        Alpha quality ({settings.avif.alpha_quality})
+                      </Label>
+                      <Slider
+                        id="avif-alpha-quality"
+                        min={1}
+                        max={100}
+                        step={1}
+                        value={[settings.avif.alpha_quality]}
+                        onValueChange={(value) =>
+                          setAvif({ alpha_quality: value[0] })
+                        }
+                      />
+                    </div>
+                  </div>
+                </div>
+
+                <div className="space-y-4">
+                  <Label className="text-base font-medium">JPEG XL Options</Label>
+
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="jxl-effort">
+                        Effort ({settings.jxl.effort})
+                      </Label>
+                      <Slider
+                        id="jxl-effort"
+                        min={1}
+                        max={10}
+                        step={1}
+                        value={[settings.jxl.effort]}
+                        onValueChange={(value) => setJxl({ effort: value[0] })}
+                      />
+                    </div>
+
+                    <div className="space-y-2">
+                      <Label htmlFor="jxl-distance">
+                        Distance (empty to use quality, 1 = visually lossless)
+                      </Label>
+                      <input
+                        id="jxl-distance"
+                        type="number"
+                        min={0}
+                        max={15}
+                        step={0.1}
+                        value={settings.jxl.distance ?? ""}
+                        onChange={(e) =>
+                          setJxl({
+                            distance: e.target.value
+                              ? Number(e.target.value)
+                              : null,
+                          })
+                        }
+                        className={selectClassName}
+                      />
+                    </div>
+                  </div>
+
+                  <label className="flex items-center gap-2 text-sm">
+                    <input
+                      type="checkbox"
+                      checked={settings.jxl.lossless_jpeg}
+                      onChange={(e) =>
+                        setJxl({ lossless_jpeg: e.target.checked })
+                      }
+                    />
+                    Transcode JPEGs losslessly (original can be restored)
+                  </label>
+                </div>
+
                 <Button onClick={save} className="w-full dark:bg-primary dark:text-black">
                   Save Settings
                 </Button>
@@ -155,7 +1219,22 @@ const SettingsPage: React.FC<SettingsPageProps> = ({ onBackToMain }) => {
                     <strong className="text-foreground">Lossless (PNG):</strong>
                     <p className="text-muted-foreground">
                       No quality loss. Best for graphics and transparency, but
-                      larger files.
+                      larger files. JPEGs stay JPEG and are optimised without
+                      re-encoding, and WebPs stay lossless WebP.
+                    </p>
+                  </div>
+                  <div>
+                    <strong className="text-foreground">Quantised PNG:</strong>
+                    <p className="text-muted-foreground">
+                      Stays PNG but drops to 256 colours or fewer. Great for
+                      screenshots and UI assets.
+                    </p>
+                  </div>
+                  <div>
+                    <strong className="text-foreground">GIF:</strong>
+                    <p className="text-muted-foreground">
+                      Optimises GIFs without losing frames or timing. Other
+                      files are skipped.
                     </p>
                   </div>
                   <div>
@@ -172,6 +1251,27 @@ const SettingsPage: React.FC<SettingsPageProps> = ({ onBackToMain }) => {
                       than lossy WebP.
                     </p>
                   </div>
+                  <div>
+                    <strong className="text-foreground">JPEG XL:</strong>
+                    <p className="text-muted-foreground">
+                      Can shrink existing JPEGs without losing a single bit.
+                      Needs a build with JPEG XL support.
+                    </p>
+                  </div>
+                  <div>
+                    <strong className="text-foreground">AVIF:</strong>
+                    <p className="text-muted-foreground">
+                      Usually the smallest files, including for JPEG photos,
+                      but much slower to encode.
+                    </p>
+                  </div>
+                  <div>
+                    <strong className="text-foreground">Keep format:</strong>
+                    <p className="text-muted-foreground">
+                      Optimises each file in the format it already is, so PNGs
+                      stay PNG and JPEGs stay JPEG. Never makes a file bigger.
+                    </p>
+                  </div>
                 </div>
               </div>
             </Card>